@@ -5,9 +5,20 @@ from __future__ import annotations
 from dataclasses import dataclass
 from typing import TYPE_CHECKING, Callable, Protocol, cast
 
+from .encoding import cards_from_mask, combine_mask
+
 OBJ_MAX_CARDS = 0
 OBJ_MIN_DEADWOOD = 1
 OBJ_FIRST_14 = 2
+OBJ_MAX_POINTS = 3
+OBJ_MIN_JOKERS = 4
+OBJ_MIN_DEADWOOD_SOFT = 5
+OBJ_WEIGHTED = 6
+OBJ_MAX_CARDS_SAFE = 7
+
+ACE_LOW = 0
+ACE_HIGH_LOW = 1
+ACE_WRAP = 2
 
 
 class MeldProtocol(Protocol):
@@ -17,6 +28,7 @@ class MeldProtocol(Protocol):
     mask_lo: int
     points: int
     jokers_used: int
+    is_pure: bool
 
 
 class CoverResultProtocol(Protocol):
@@ -38,9 +50,16 @@ class CoverResultProtocol(Protocol):
     def used_jokers(self) -> int:  # pragma: no cover - protocol only
         ...
 
+    @property
+    def deadwood(self) -> list[int]:  # pragma: no cover - protocol only
+        ...
+
+    def count_pure_melds(self) -> int:  # pragma: no cover - protocol only
+        ...
+
 
 native_best_cover: Callable[[int, int, int, int], CoverResultProtocol] | None = None
-native_enumerate_melds: Callable[[int, int], list[MeldProtocol]] | None = None
+native_enumerate_melds: Callable[[int, int, int], list[MeldProtocol]] | None = None
 
 if TYPE_CHECKING:  # pragma: no cover - typing helper
     pass
@@ -52,7 +71,9 @@ try:  # pragma: no cover - optional dependency
     native_best_cover = cast(
         Callable[[int, int, int, int], CoverResultProtocol], _native_best_cover
     )
-    native_enumerate_melds = cast(Callable[[int, int], list[MeldProtocol]], _native_enumerate_melds)
+    native_enumerate_melds = cast(
+        Callable[[int, int, int], list[MeldProtocol]], _native_enumerate_melds
+    )
 except Exception:  # pragma: no cover - optional dependency
     native_best_cover = None
     native_enumerate_melds = None
@@ -67,21 +88,31 @@ class _FallbackCoverResult(CoverResultProtocol):
     covered_cards: int
     total_points: int
     used_jokers: int
+    deadwood: list[int]
+
+    def count_pure_melds(self) -> int:
+        return sum(1 for meld in self.melds if meld.is_pure)
 
 
-def enumerate_melds(mask_hi: int, mask_lo: int) -> list[MeldProtocol]:
+def enumerate_melds(mask_hi: int, mask_lo: int, ace_mode: int = ACE_LOW) -> list[MeldProtocol]:
     """Return all melds contained in the provided bit masks."""
 
     if native_enumerate_melds is None:
         return []
-    return list(native_enumerate_melds(mask_hi, mask_lo))
+    return list(native_enumerate_melds(mask_hi, mask_lo, ace_mode))
 
 
 def best_cover(mask_hi: int, mask_lo: int, objective: int, threshold: int) -> CoverResultProtocol:
     """Return the solver cover for ``objective`` or a fallback if unavailable."""
 
     if native_best_cover is None:
-        return _FallbackCoverResult(melds=[], covered_cards=0, total_points=0, used_jokers=0)
+        return _FallbackCoverResult(
+            melds=[],
+            covered_cards=0,
+            total_points=0,
+            used_jokers=0,
+            deadwood=cards_from_mask(combine_mask(mask_hi, mask_lo)),
+        )
     return native_best_cover(mask_hi, mask_lo, objective, threshold)
 
 
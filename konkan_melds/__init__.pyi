@@ -1,17 +1,316 @@
-from typing import List
+from typing import Callable, List, Optional, Sequence, Tuple
+
+OBJ_MAX_CARDS: int
+OBJ_MIN_DEADWOOD: int
+OBJ_FIRST_14: int
+OBJ_MAX_POINTS: int
+OBJ_MIN_JOKERS: int
+OBJ_MIN_DEADWOOD_SOFT: int
+OBJ_WEIGHTED: int
+OBJ_MAX_CARDS_SAFE: int
+KIND_SET: int
+KIND_RUN: int
+KIND_PARTIAL: int
 
 class Meld:
     mask_hi: int
     mask_lo: int
     points: int
     jokers_used: int
+    wilds_used: int
     kind: int
+    is_pure: bool
+    points_by_card: List[Tuple[int, int]]
+    joker_ids: List[int]
+    cards_needed: int
+    def __init__(
+        self, mask_hi: int, mask_lo: int, points: int, jokers_used: int, kind: int
+    ) -> None: ...
+    def card_points(self) -> List[Tuple[int, int]]: ...
+    # Available when built with the `serde` cargo feature.
+    def to_json(self) -> str: ...
+    @staticmethod
+    def from_json(data: str) -> Meld: ...
 
 class CoverResult:
     melds: List[Meld]
     covered_cards: int
     total_points: int
     used_jokers: int
+    deadwood: List[int]
+    deadwood_count: int
+    meets_threshold: bool
+    target_met: bool
+    jokers_only: bool
+    empty_hand: bool
+    timed_out: bool
+    infeasible: bool
+    truncated: bool
+    candidates: List[Meld]
+    def count_pure_melds(self) -> int: ...
+    def coverage_mask(self) -> Tuple[int, int]: ...
+    # Available when built with the `serde` cargo feature.
+    def to_json(self) -> str: ...
+    @staticmethod
+    def from_json(data: str) -> CoverResult: ...
+
+def enumerate_melds(
+    mask_hi: int,
+    mask_lo: int,
+    ace_mode: int = ...,
+    num_decks: int = ...,
+    num_jokers: int = ...,
+    rank_points: Optional[Sequence[int]] = ...,
+    wild_rank: Optional[int] = ...,
+    only_maximal_runs: bool = ...,
+    max_run_len: int = ...,
+    order: int = ...,
+    min_set_size: int = ...,
+    max_set_size: int = ...,
+    meld_limit: int = ...,
+    max_jokers_per_meld: int = ...,
+    include_partials: bool = ...,
+    joker_bonus: Optional[Tuple[int, int]] = ...,
+) -> List[Meld]: ...
+def enumerate_melds_from_ids(
+    card_ids: Sequence[int],
+    ace_mode: int = ...,
+    num_decks: int = ...,
+    num_jokers: int = ...,
+) -> List[Meld]: ...
+def enumerate_runs(
+    mask_hi: int,
+    mask_lo: int,
+    ace_mode: int = ...,
+    num_decks: int = ...,
+    num_jokers: int = ...,
+    rank_points: Optional[Sequence[int]] = ...,
+    wild_rank: Optional[int] = ...,
+    only_maximal_runs: bool = ...,
+    max_run_len: int = ...,
+    order: int = ...,
+    collapse_copies: bool = ...,
+    max_jokers_per_meld: int = ...,
+    joker_bonus: Optional[Tuple[int, int]] = ...,
+) -> List[Meld]: ...
+class EnumStats:
+    sets: int
+    runs: int
+    duplicates: int
+    longest_run: int
+    truncated: bool
+
+def enumerate_melds_stats(
+    mask_hi: int,
+    mask_lo: int,
+    ace_mode: int = ...,
+    num_decks: int = ...,
+    num_jokers: int = ...,
+    rank_points: Optional[Sequence[int]] = ...,
+    wild_rank: Optional[int] = ...,
+    order: int = ...,
+    meld_limit: int = ...,
+    joker_bonus: Optional[Tuple[int, int]] = ...,
+) -> Tuple[List[Meld], EnumStats]: ...
+def enumerate_sets(
+    mask_hi: int,
+    mask_lo: int,
+    num_decks: int = ...,
+    num_jokers: int = ...,
+    rank_points: Optional[Sequence[int]] = ...,
+    wild_rank: Optional[int] = ...,
+    order: int = ...,
+    min_set_size: int = ...,
+    max_set_size: int = ...,
+    dedup_joker_assignments: bool = ...,
+    max_jokers_per_meld: int = ...,
+    joker_bonus: Optional[Tuple[int, int]] = ...,
+) -> List[Meld]: ...
+def count_melds(
+    mask_hi: int, mask_lo: int, ace_mode: int = ..., num_decks: int = ..., num_jokers: int = ...
+) -> int: ...
+def melds_by_card(
+    mask_hi: int,
+    mask_lo: int,
+    ace_mode: int = ...,
+    num_decks: int = ...,
+    num_jokers: int = ...,
+    rank_points: Optional[Sequence[int]] = ...,
+    wild_rank: Optional[int] = ...,
+    joker_bonus: Optional[Tuple[int, int]] = ...,
+) -> List[Tuple[int, List[int]]]: ...
+def melds_containing(
+    mask_hi: int, mask_lo: int, card_id: int, ace_mode: int = ...
+) -> List[Meld]: ...
+
+class MeldIterator:
+    def __iter__(self) -> MeldIterator: ...
+    def __next__(self) -> Meld: ...
+
+class Solver:
+    def __init__(
+        self,
+        ace_mode: int = ...,
+        num_decks: int = ...,
+        num_jokers: int = ...,
+        rank_points: Optional[Sequence[int]] = ...,
+        wild_rank: Optional[int] = ...,
+        joker_bonus: Optional[Tuple[int, int]] = ...,
+    ) -> None: ...
+    def enumerate(self, mask_hi: int, mask_lo: int) -> List[Meld]: ...
 
-def enumerate_melds(mask_hi: int, mask_lo: int) -> List[Meld]: ...
-def best_cover(mask_hi: int, mask_lo: int, objective: int, threshold: int) -> CoverResult: ...
+def iter_melds(
+    mask_hi: int,
+    mask_lo: int,
+    ace_mode: int = ...,
+    num_decks: int = ...,
+    num_jokers: int = ...,
+    rank_points: Optional[Sequence[int]] = ...,
+    wild_rank: Optional[int] = ...,
+    joker_bonus: Optional[Tuple[int, int]] = ...,
+) -> MeldIterator: ...
+def layoff_candidates(
+    hand_hi: int,
+    hand_lo: int,
+    table_melds: Sequence[Tuple[int, int]],
+    swap_jokers: bool = ...,
+    ace_mode: int = ...,
+) -> List[Tuple[int, int, int, Optional[int]]]: ...
+def joker_swaps(
+    hand_hi: int, hand_lo: int, meld_hi: int, meld_lo: int, ace_mode: int = ...
+) -> List[Tuple[int, int]]: ...
+def melds_gained_by_draw(
+    mask_hi: int, mask_lo: int, candidate_ids: Sequence[int], ace_mode: int = ...
+) -> List[Tuple[int, List[Meld]]]: ...
+def near_runs(
+    mask_hi: int, mask_lo: int, max_missing: int = ..., ace_mode: int = ...
+) -> List[Tuple[List[int], List[int]]]: ...
+def classify_meld(mask_hi: int, mask_lo: int, ace_mode: int = ...) -> Optional[int]: ...
+def validate_meld_with_jokers(card_ids: Sequence[int], kind: int, ace_mode: int = ...) -> bool: ...
+def best_cover(
+    mask_hi: int,
+    mask_lo: int,
+    objective: int,
+    threshold: int,
+    rank_points: Optional[Sequence[int]] = ...,
+    min_first_points: int = ...,
+    forbidden_hi: int = ...,
+    forbidden_lo: int = ...,
+    max_melds: int = ...,
+    num_decks: int = ...,
+    num_jokers: int = ...,
+    joker_penalty: int = ...,
+    target_cards: int = ...,
+    meld_limit: int = ...,
+    max_jokers_per_meld: int = ...,
+    joker_bonus: Optional[Tuple[int, int]] = ...,
+) -> CoverResult: ...
+def best_cover_constrained(
+    mask_hi: int,
+    mask_lo: int,
+    objective: int,
+    threshold: int,
+    required_hi: int,
+    required_lo: int,
+    rank_points: Optional[Sequence[int]] = ...,
+    min_first_points: int = ...,
+    forbidden_hi: int = ...,
+    forbidden_lo: int = ...,
+    max_melds: int = ...,
+    num_decks: int = ...,
+    num_jokers: int = ...,
+    joker_penalty: int = ...,
+    target_cards: int = ...,
+    meld_limit: int = ...,
+    max_jokers_per_meld: int = ...,
+    joker_bonus: Optional[Tuple[int, int]] = ...,
+) -> CoverResult: ...
+def best_cover_parallel(
+    mask_hi: int, mask_lo: int, objective: int, threshold: int
+) -> CoverResult: ...
+def best_cover_dp(mask_hi: int, mask_lo: int, objective: int, threshold: int) -> CoverResult: ...
+def best_single_meld(mask_hi: int, mask_lo: int, objective: int) -> Optional[Meld]: ...
+def compare_covers(a: CoverResult, b: CoverResult, objective: int) -> int: ...
+def best_cover_batch(
+    hands: Sequence[Tuple[int, int]], objective: int, threshold: int
+) -> List[CoverResult]: ...
+def best_cover_timed(
+    mask_hi: int, mask_lo: int, objective: int, threshold: int, time_budget_ms: int
+) -> CoverResult: ...
+def best_cover_weighted(
+    mask_hi: int, mask_lo: int, card_weight: float, point_weight: float, joker_weight: float
+) -> CoverResult: ...
+def best_cover_with_progress(
+    mask_hi: int,
+    mask_lo: int,
+    objective: int,
+    threshold: int,
+    callback: Callable[[int, int], object],
+) -> CoverResult: ...
+def best_cover_joker_limited(
+    mask_hi: int, mask_lo: int, max_jokers: int, objective: int, threshold: int
+) -> CoverResult: ...
+def best_cover_pure(
+    mask_hi: int, mask_lo: int, objective: int, threshold: int
+) -> CoverResult: ...
+def best_cover_delta(
+    prev_result: CoverResult,
+    mask_hi: int,
+    mask_lo: int,
+    changed_id: int,
+    objective: int,
+    threshold: int,
+) -> CoverResult: ...
+def min_deadwood(mask_hi: int, mask_lo: int, max_jokers: int = ...) -> int: ...
+def can_go_out(mask_hi: int, mask_lo: int) -> bool: ...
+def go_out_layout(mask_hi: int, mask_lo: int) -> Optional[CoverResult]: ...
+def rank_draws(
+    hand_hi: int, hand_lo: int, unseen_hi: int, unseen_lo: int, objective: int
+) -> List[Tuple[int, float]]: ...
+def best_discard(
+    mask_hi: int, mask_lo: int, objective: int, threshold: int
+) -> Tuple[int, CoverResult]: ...
+def discard_safety(
+    discard_id: int,
+    opponent_discards_hi: int,
+    opponent_discards_lo: int,
+    ace_mode: int = ...,
+) -> int: ...
+def best_covers(
+    mask_hi: int, mask_lo: int, objective: int, threshold: int, k: int
+) -> List[CoverResult]: ...
+def all_maximal_covers(mask_hi: int, mask_lo: int, limit: int) -> List[CoverResult]: ...
+def parse_card(card: str) -> int: ...
+def parse_hand(cards: Sequence[str]) -> Tuple[int, int]: ...
+def card_to_string(card_id: int) -> str: ...
+def mask_to_strings(mask_hi: int, mask_lo: int) -> List[str]: ...
+def build_mask(card_ids: Sequence[int], strict: bool = ...) -> Tuple[int, int]: ...
+def mask_to_cards(mask_hi: int, mask_lo: int) -> List[int]: ...
+def melds_overlap(a_hi: int, a_lo: int, b_hi: int, b_lo: int) -> bool: ...
+def melds_disjoint_all(masks: Sequence[Tuple[int, int]]) -> bool: ...
+def meld_to_bytes(meld: Meld) -> bytes: ...
+def meld_from_bytes(data: bytes) -> Meld: ...
+def cover_to_bytes(cover: CoverResult) -> bytes: ...
+def cover_from_bytes(data: bytes) -> CoverResult: ...
+def union_hands(
+    a_hi: int, a_lo: int, b_hi: int, b_lo: int
+) -> Tuple[int, int, List[int]]: ...
+def canonical_hand_key(
+    mask_hi: int, mask_lo: int, num_decks: int = ..., num_jokers: int = ...
+) -> int: ...
+def deadwood_points(
+    hand_hi: int,
+    hand_lo: int,
+    covered_hi: int,
+    covered_lo: int,
+    joker_penalty: int = ...,
+    rank_points: Optional[Sequence[int]] = ...,
+) -> int: ...
+def deadwood_breakdown(
+    hand_hi: int,
+    hand_lo: int,
+    covered_hi: int,
+    covered_lo: int,
+    joker_penalty: int = ...,
+    rank_points: Optional[Sequence[int]] = ...,
+) -> List[Tuple[int, int, int]]: ...
@@ -0,0 +1,137 @@
+//! Compact little-endian byte layouts for melds and covers, for components
+//! that exchange them with the solver without going through Python.
+//!
+//! A meld takes `MELD_BYTES` bytes: its 128-bit mask (`mask_lo` first), its
+//! points as an `i32`, its joker count and its kind. A cover is a `u16` meld
+//! count, that many melds, its 128-bit deadwood mask and a byte of flags.
+//! Readers built against older versions rely on these layouts, so they never
+//! change.
+
+use crate::bitset::{card_bitmask, combine_mask, mask_cards, merge_words};
+use crate::{CoverResult, Meld};
+
+pub const MELD_BYTES: usize = 22;
+
+const FLAG_MEETS_THRESHOLD: u8 = 1 << 0;
+const FLAG_TARGET_MET: u8 = 1 << 1;
+const FLAG_JOKERS_ONLY: u8 = 1 << 2;
+const FLAG_EMPTY_HAND: u8 = 1 << 3;
+const FLAG_TIMED_OUT: u8 = 1 << 4;
+const FLAG_INFEASIBLE: u8 = 1 << 5;
+const KNOWN_FLAGS: u8 = (1 << 6) - 1;
+
+pub fn meld_to_bytes(meld: &Meld) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(MELD_BYTES);
+    write_meld(meld, &mut bytes);
+    bytes
+}
+
+fn write_meld(meld: &Meld, out: &mut Vec<u8>) {
+    out.extend_from_slice(&merge_words(meld.mask_hi, meld.mask_lo).to_le_bytes());
+    out.extend_from_slice(&meld.points.to_le_bytes());
+    out.push(meld.jokers_used);
+    out.push(meld.kind);
+}
+
+/// Reads a meld written by `meld_to_bytes`. The layout carries neither wild
+/// cards nor per-card points, so the meld comes back as `Meld::new` builds
+/// it.
+pub fn meld_from_bytes(bytes: &[u8]) -> Result<Meld, String> {
+    let bytes: &[u8; MELD_BYTES] = bytes
+        .try_into()
+        .map_err(|_| format!("a meld takes {MELD_BYTES} bytes, got {}", bytes.len()))?;
+    read_meld(bytes)
+}
+
+fn read_meld(bytes: &[u8; MELD_BYTES]) -> Result<Meld, String> {
+    let (mask_bytes, rest) = bytes.split_at(16);
+    let mask = u128::from_le_bytes(mask_bytes.try_into().expect("16 mask bytes"));
+    let points = i32::from_le_bytes(rest[..4].try_into().expect("4 point bytes"));
+    let (jokers_used, kind) = (rest[4], rest[5]);
+    let (mask_hi, mask_lo) = combine_mask(mask);
+    Meld::new(mask_hi, mask_lo, points, jokers_used, kind)
+}
+
+pub fn cover_to_bytes(cover: &CoverResult) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(2 + cover.melds.len() * MELD_BYTES + 17);
+    // No hand holds anywhere near `u16::MAX` disjoint melds.
+    bytes.extend_from_slice(&(cover.melds.len() as u16).to_le_bytes());
+    for meld in &cover.melds {
+        write_meld(meld, &mut bytes);
+    }
+    let deadwood = cover
+        .deadwood
+        .iter()
+        .fold(0u128, |mask, &card_id| mask | card_bitmask(card_id));
+    bytes.extend_from_slice(&deadwood.to_le_bytes());
+    let flags = [
+        (cover.meets_threshold, FLAG_MEETS_THRESHOLD),
+        (cover.target_met, FLAG_TARGET_MET),
+        (cover.jokers_only, FLAG_JOKERS_ONLY),
+        (cover.empty_hand, FLAG_EMPTY_HAND),
+        (cover.timed_out, FLAG_TIMED_OUT),
+        (cover.infeasible, FLAG_INFEASIBLE),
+    ]
+    .into_iter()
+    .filter(|&(set, _)| set)
+    .fold(0u8, |flags, (_, flag)| flags | flag);
+    bytes.push(flags);
+    bytes
+}
+
+/// Reads a cover written by `cover_to_bytes`. Its melds come back as
+/// `meld_from_bytes` returns them, the card, point and joker totals are
+/// summed from those melds, and `truncated`, which the layout leaves out,
+/// comes back unset.
+pub fn cover_from_bytes(bytes: &[u8]) -> Result<CoverResult, String> {
+    let Some((count_bytes, rest)) = bytes.split_first_chunk::<2>() else {
+        return Err(format!(
+            "a cover takes at least 19 bytes, got {}",
+            bytes.len()
+        ));
+    };
+    let count = u16::from_le_bytes(*count_bytes) as usize;
+    let expected = 2 + count * MELD_BYTES + 17;
+    if bytes.len() != expected {
+        return Err(format!(
+            "a cover of {count} melds takes {expected} bytes, got {}",
+            bytes.len()
+        ));
+    }
+    let (meld_bytes, rest) = rest.split_at(count * MELD_BYTES);
+    let melds = meld_bytes
+        .chunks_exact(MELD_BYTES)
+        .map(|chunk| read_meld(chunk.try_into().expect("chunk of MELD_BYTES")))
+        .collect::<Result<Vec<Meld>, String>>()?;
+    let deadwood = u128::from_le_bytes(rest[..16].try_into().expect("16 deadwood bytes"));
+    let flags = rest[16];
+    if flags & !KNOWN_FLAGS != 0 {
+        return Err(format!("unknown cover flags {:#04x}", flags & !KNOWN_FLAGS));
+    }
+    // Saturating, since nothing stops the bytes from repeating a meld.
+    let covered_cards = melds.iter().fold(0u8, |total, meld| {
+        total.saturating_add(merge_words(meld.mask_hi, meld.mask_lo).count_ones() as u8)
+    });
+    let total_points = melds
+        .iter()
+        .fold(0i32, |total, meld| total.saturating_add(meld.points));
+    let used_jokers = melds
+        .iter()
+        .fold(0u8, |total, meld| total.saturating_add(meld.jokers_used));
+    Ok(CoverResult {
+        melds,
+        covered_cards,
+        total_points,
+        used_jokers,
+        deadwood: mask_cards(deadwood),
+        deadwood_count: deadwood.count_ones() as u8,
+        meets_threshold: flags & FLAG_MEETS_THRESHOLD != 0,
+        target_met: flags & FLAG_TARGET_MET != 0,
+        jokers_only: flags & FLAG_JOKERS_ONLY != 0,
+        empty_hand: flags & FLAG_EMPTY_HAND != 0,
+        timed_out: flags & FLAG_TIMED_OUT != 0,
+        infeasible: flags & FLAG_INFEASIBLE != 0,
+        truncated: false,
+        candidates: Vec::new(),
+    })
+}
@@ -14,3 +14,60 @@ pub fn combine_mask(mask: u128) -> (u64, u64) {
 pub fn merge_words(mask_hi: u64, mask_lo: u64) -> u128 {
     ((mask_hi as u128) << 64) | mask_lo as u128
 }
+
+/// ORs the given card IDs into a mask. IDs must lie in `0..card_count`; with
+/// `strict`, listing the same ID twice is an error rather than a no-op.
+pub fn build_mask(card_ids: &[u8], card_count: usize, strict: bool) -> Result<u128, String> {
+    let mut mask = 0u128;
+    for &card_id in card_ids {
+        if card_id as usize >= card_count {
+            return Err(format!("card id {card_id} is outside 0..{card_count}"));
+        }
+        let bit = card_bitmask(card_id);
+        if strict && mask & bit != 0 {
+            return Err(format!("card id {card_id} listed twice"));
+        }
+        mask |= bit;
+    }
+    Ok(mask)
+}
+
+pub fn mask_cards(mask: u128) -> Vec<u8> {
+    let mut cards = Vec::with_capacity(mask.count_ones() as usize);
+    let mut remaining = mask;
+    while remaining != 0 {
+        cards.push(remaining.trailing_zeros() as u8);
+        remaining &= remaining - 1;
+    }
+    cards
+}
+
+/// Merges two hands given as `(hi, lo)` words, returning the merged words and
+/// the card IDs present in both. Jokers sit at the top of the high word, and
+/// each printed joker has its own ID, so two different jokers never collide.
+pub fn union_hands(a_hi: u64, a_lo: u64, b_hi: u64, b_lo: u64) -> (u64, u64, Vec<u8>) {
+    let a = merge_words(a_hi, a_lo);
+    let b = merge_words(b_hi, b_lo);
+    let (hi, lo) = combine_mask(a | b);
+    (hi, lo, mask_cards(a & b))
+}
+
+/// Whether two masks share a card, so the melds they stand for cannot both
+/// be laid down.
+pub fn melds_overlap(a_hi: u64, a_lo: u64, b_hi: u64, b_lo: u64) -> bool {
+    merge_words(a_hi, a_lo) & merge_words(b_hi, b_lo) != 0
+}
+
+/// Whether no card appears in two of the masks, as in a layout that can be
+/// laid down whole. Stops at the first card seen twice.
+pub fn melds_disjoint_all(masks: &[(u64, u64)]) -> bool {
+    let mut used = 0u128;
+    for &(mask_hi, mask_lo) in masks {
+        let mask = merge_words(mask_hi, mask_lo);
+        if used & mask != 0 {
+            return false;
+        }
+        used |= mask;
+    }
+    true
+}
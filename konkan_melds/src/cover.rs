@@ -1,45 +1,159 @@
 //! Search utilities for selecting the best meld cover under various objectives.
 
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
 
-use crate::bitset::merge_words;
-use crate::runs_sets::enumerate_melds;
-use crate::{CoverResult, Meld, OBJ_FIRST_14, OBJ_MAX_CARDS, OBJ_MIN_DEADWOOD};
+use rayon::prelude::*;
+
+use crate::bitset::{card_bitmask, combine_mask, mask_cards, merge_words};
+use crate::deck::{decode_card, DeckConfig, Scoring, KIND_PARTIAL, NUM_RANKS};
+use crate::runs_sets::{
+    enumerate_melds, enumerate_melds_capped, melds_after_change, melds_gained_by_draw,
+    run_neighbours, EnumOptions,
+};
+use crate::{
+    CoverResult, Meld, OBJ_FIRST_14, OBJ_MAX_CARDS, OBJ_MAX_CARDS_SAFE, OBJ_MAX_POINTS,
+    OBJ_MIN_DEADWOOD, OBJ_MIN_DEADWOOD_SOFT, OBJ_MIN_JOKERS, OBJ_WEIGHTED,
+};
 
 #[derive(Clone, Copy)]
 struct Score {
+    meets_first_points: bool,
     meets_threshold: bool,
     target_met: bool,
     covered_cards: u8,
     deadwood: u8,
     total_points: i32,
     used_jokers: u8,
+    /// `CoverOptions::joker_penalty` for each joker left unmelded, set
+    /// against the melded points under `OBJ_MIN_DEADWOOD`.
+    joker_penalty: i32,
+    /// Near-melds among the leftover cards; only `OBJ_MIN_DEADWOOD_SOFT`
+    /// fills it in, and only that objective reads it.
+    near_melds: u16,
+    /// Value of the costliest leftover card; only `OBJ_MAX_CARDS_SAFE` fills
+    /// it in, and only that objective reads it.
+    max_leftover: i32,
+    /// `CoverWeights::score` of the selection, read by `OBJ_WEIGHTED` only.
+    weighted: f64,
+}
+
+impl Score {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        covered_cards: u8,
+        total_points: i32,
+        used_jokers: u8,
+        total_cards: u8,
+        target_cards: u8,
+        threshold: i32,
+        min_first_points: i32,
+        joker_penalty: i32,
+    ) -> Self {
+        Score {
+            // A requirement of zero or below asks for nothing, as in
+            // `empty_cover`. Otherwise a point table with negative values
+            // would leave every cover short of 0 points behind the empty one.
+            meets_first_points: min_first_points <= 0 || total_points >= min_first_points,
+            meets_threshold: threshold <= 0 || total_points >= threshold,
+            target_met: covered_cards >= target_cards,
+            covered_cards,
+            deadwood: total_cards.saturating_sub(covered_cards),
+            total_points,
+            used_jokers,
+            joker_penalty,
+            near_melds: 0,
+            max_leftover: 0,
+            weighted: 0.0,
+        }
+    }
+
+    /// Melded points net of the unmelded-joker charge, widened so that no
+    /// point table can overflow it.
+    fn net_points(&self) -> i64 {
+        self.total_points as i64 - self.joker_penalty as i64
+    }
+}
+
+/// Pairs of leftover natural cards one draw away from a meld together: two
+/// cards of a rank, or two cards of a suit at most two ranks apart, as in
+/// 5♠ 6♠ or 5♠ 7♠. Jokers and wild cards are left out, and ranks only
+/// connect through the ace where `ace_mode` lets a run do so.
+fn near_melds(leftover: u128, deck: &DeckConfig, ace_mode: u8) -> u16 {
+    let cards: Vec<(u8, u8)> = mask_cards(leftover)
+        .into_iter()
+        .map(|card_id| decode_card(card_id, deck))
+        .filter(|card| !deck.is_wild(card))
+        .filter_map(|card| Some((card.rank?, card.suit?)))
+        .collect();
+    let mut count = 0;
+    for (idx, &(rank, suit)) in cards.iter().enumerate() {
+        for &(other_rank, other_suit) in &cards[idx + 1..] {
+            if rank == other_rank
+                || (suit == other_suit && run_neighbours(rank, other_rank, ace_mode))
+            {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+/// Value of the costliest card in `leftover`, valued as
+/// `Scoring::deadwood_points` values it; 0 when nothing is left.
+fn max_leftover(leftover: u128, deck: &DeckConfig, scoring: &Scoring, joker_penalty: i32) -> i32 {
+    mask_cards(leftover)
+        .into_iter()
+        .map(|card_id| match decode_card(card_id, deck).rank {
+            Some(rank) => scoring.points_for_rank(rank),
+            None => joker_penalty,
+        })
+        .max()
+        .unwrap_or(0)
 }
 
 fn better_score(objective: u8, new: &Score, best: &Score) -> bool {
+    // Covers short of the opening minimum are invalid under every objective.
+    match (new.meets_first_points, best.meets_first_points) {
+        (true, false) => return true,
+        (false, true) => return false,
+        _ => {}
+    }
     match objective {
-        OBJ_MIN_DEADWOOD => match (new.meets_threshold, best.meets_threshold) {
-            (true, false) => true,
-            (false, true) => false,
-            _ => {
-                match new.deadwood.cmp(&best.deadwood) {
-                    Ordering::Less => return true,
-                    Ordering::Greater => return false,
-                    Ordering::Equal => {}
-                }
-                match new.total_points.cmp(&best.total_points) {
-                    Ordering::Greater => return true,
-                    Ordering::Less => return false,
-                    Ordering::Equal => {}
-                }
-                match new.covered_cards.cmp(&best.covered_cards) {
-                    Ordering::Greater => return true,
-                    Ordering::Less => return false,
-                    Ordering::Equal => {}
+        OBJ_MIN_DEADWOOD | OBJ_MIN_DEADWOOD_SOFT => {
+            match (new.meets_threshold, best.meets_threshold) {
+                (true, false) => true,
+                (false, true) => false,
+                _ => {
+                    match new.deadwood.cmp(&best.deadwood) {
+                        Ordering::Less => return true,
+                        Ordering::Greater => return false,
+                        Ordering::Equal => {}
+                    }
+                    if objective == OBJ_MIN_DEADWOOD_SOFT {
+                        match new.near_melds.cmp(&best.near_melds) {
+                            Ordering::Greater => return true,
+                            Ordering::Less => return false,
+                            Ordering::Equal => {}
+                        }
+                    }
+                    match new.net_points().cmp(&best.net_points()) {
+                        Ordering::Greater => return true,
+                        Ordering::Less => return false,
+                        Ordering::Equal => {}
+                    }
+                    match new.covered_cards.cmp(&best.covered_cards) {
+                        Ordering::Greater => return true,
+                        Ordering::Less => return false,
+                        Ordering::Equal => {}
+                    }
+                    new.used_jokers < best.used_jokers
                 }
-                new.used_jokers < best.used_jokers
             }
-        },
+        }
         OBJ_FIRST_14 => match (new.target_met, best.target_met) {
             (true, false) => true,
             (false, true) => false,
@@ -62,7 +176,53 @@ fn better_score(objective: u8, new: &Score, best: &Score) -> bool {
                 new.used_jokers < best.used_jokers
             }
         },
-        OBJ_MAX_CARDS | _ => {
+        OBJ_MAX_CARDS_SAFE => {
+            match new.covered_cards.cmp(&best.covered_cards) {
+                Ordering::Greater => return true,
+                Ordering::Less => return false,
+                Ordering::Equal => {}
+            }
+            match new.max_leftover.cmp(&best.max_leftover) {
+                Ordering::Less => true,
+                Ordering::Greater => false,
+                Ordering::Equal => better_score(OBJ_MAX_CARDS, new, best),
+            }
+        }
+        // Ties on the weighted score fall back to the `OBJ_MAX_CARDS` order.
+        OBJ_WEIGHTED => match new.weighted.total_cmp(&best.weighted) {
+            Ordering::Greater => true,
+            Ordering::Less => false,
+            Ordering::Equal => better_score(OBJ_MAX_CARDS, new, best),
+        },
+        // The threshold plays no part here: the points are the objective itself.
+        OBJ_MAX_POINTS => {
+            match new.total_points.cmp(&best.total_points) {
+                Ordering::Greater => return true,
+                Ordering::Less => return false,
+                Ordering::Equal => {}
+            }
+            match new.covered_cards.cmp(&best.covered_cards) {
+                Ordering::Greater => return true,
+                Ordering::Less => return false,
+                Ordering::Equal => {}
+            }
+            new.used_jokers < best.used_jokers
+        }
+        // Jokers held back outrank coverage, so a smaller natural cover can win.
+        OBJ_MIN_JOKERS => {
+            match new.used_jokers.cmp(&best.used_jokers) {
+                Ordering::Less => return true,
+                Ordering::Greater => return false,
+                Ordering::Equal => {}
+            }
+            match new.covered_cards.cmp(&best.covered_cards) {
+                Ordering::Greater => return true,
+                Ordering::Less => return false,
+                Ordering::Equal => {}
+            }
+            new.total_points > best.total_points
+        }
+        _ => {
             match new.covered_cards.cmp(&best.covered_cards) {
                 Ordering::Greater => return true,
                 Ordering::Less => return false,
@@ -83,57 +243,475 @@ fn better_score(objective: u8, new: &Score, best: &Score) -> bool {
     }
 }
 
-fn update_best(
+type Best = Option<Candidate>;
+
+/// Nodes visited between two looks at the clock when a time budget is set.
+const DEADLINE_CHECK_INTERVAL: u32 = 1024;
+
+/// Shortest gap between two calls of `CoverOptions::on_progress`.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Cards a cover melds to meet the `OBJ_FIRST_14` target in a 14-card deal.
+pub const DEFAULT_TARGET_CARDS: u8 = 14;
+
+/// Called with the covered cards and points of a new best cover.
+pub type ProgressCallback = Arc<dyn Fn(u8, i32) + Send + Sync>;
+
+/// Weights of the `OBJ_WEIGHTED` score. None may be negative: the search
+/// prunes on the assumption that more cards and points and fewer jokers never
+/// score less.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CoverWeights {
+    pub card: f64,
+    pub point: f64,
+    pub joker: f64,
+}
+
+impl CoverWeights {
+    fn score(&self, covered_cards: u8, total_points: i32, used_jokers: u8) -> f64 {
+        self.card * covered_cards as f64 + self.point * total_points as f64
+            - self.joker * used_jokers as f64
+    }
+}
+
+/// Every covered card counts once, which orders covers as `OBJ_MAX_CARDS`.
+impl Default for CoverWeights {
+    fn default() -> Self {
+        CoverWeights {
+            card: 1.0,
+            point: 0.0,
+            joker: 0.0,
+        }
+    }
+}
+
+/// Per-call knobs for the cover search. The default reproduces `best_cover`.
+#[derive(Clone)]
+pub struct CoverOptions {
+    /// Rules used to enumerate the candidate melds.
+    pub enumeration: EnumOptions,
+    /// Opening requirement: covers totalling fewer points are only returned
+    /// when no cover reaches it. This gate is checked before any objective,
+    /// so under `OBJ_MIN_DEADWOOD` it takes precedence over `threshold`, which
+    /// still orders the covers that pass (or all fail) the gate.
+    pub min_first_points: i32,
+    /// Stop exploring once this much time has passed and keep the best so far.
+    pub time_budget: Option<Duration>,
+    /// Cards every returned cover must meld. When no selection covers them
+    /// all, the result is an empty cover flagged `infeasible`.
+    pub required_mask: u128,
+    /// Cards held back from every meld, such as a joker kept in reserve.
+    pub forbidden_mask: u128,
+    /// Most melds a cover may lay down at once; 0 means no limit.
+    pub max_melds: usize,
+    /// Most jokers a cover may spend, banking the rest; `None` means no limit.
+    pub max_jokers: Option<u8>,
+    /// Points charged under `OBJ_MIN_DEADWOOD` for each joker a cover leaves
+    /// in hand (commonly `DEFAULT_JOKER_PENALTY`). Among covers leaving the
+    /// same number of cards, the one with the most melded points net of this
+    /// penalty wins; 0 leaves jokers uncharged. It must lie in
+    /// `0..=MAX_JOKER_PENALTY`: the search bounds a cover by charging its
+    /// jokers nothing, which only holds for a penalty that is never a bonus.
+    pub joker_penalty: i32,
+    /// Told about each new best cover of the sequential search, at most once
+    /// per `PROGRESS_INTERVAL`, so improvements found in a burst are skipped.
+    pub on_progress: Option<ProgressCallback>,
+    /// Weights of the `OBJ_WEIGHTED` score; other objectives ignore them.
+    pub weights: CoverWeights,
+    /// Search only melds without jokers or wild cards, keeping every
+    /// substitute in hand.
+    pub pure_only: bool,
+    /// Cards a cover must meld to meet the `OBJ_FIRST_14` target and set
+    /// `target_met`, for variants dealing other than 14 cards.
+    pub target_cards: u8,
+}
+
+impl Default for CoverOptions {
+    fn default() -> Self {
+        CoverOptions {
+            enumeration: EnumOptions::default(),
+            min_first_points: 0,
+            time_budget: None,
+            required_mask: 0,
+            forbidden_mask: 0,
+            max_melds: 0,
+            max_jokers: None,
+            joker_penalty: 0,
+            on_progress: None,
+            weights: CoverWeights::default(),
+            pure_only: false,
+            target_cards: DEFAULT_TARGET_CARDS,
+        }
+    }
+}
+
+/// Everything a `Score` depends on besides the selection being scored.
+#[derive(Clone, Copy)]
+struct Scorer {
     objective: u8,
     threshold: i32,
+    min_first_points: i32,
+    joker_penalty: i32,
     total_cards: u8,
+    target_cards: u8,
+    /// Jokers in the hand, melded or not.
+    hand_jokers: u8,
+    hand_mask: u128,
+    deck: DeckConfig,
+    scoring: Scoring,
+    ace_mode: u8,
+    weights: CoverWeights,
+}
+
+impl Scorer {
+    fn new(objective: u8, threshold: i32, hand_mask: u128, options: &CoverOptions) -> Self {
+        Scorer {
+            objective,
+            threshold,
+            min_first_points: options.min_first_points,
+            joker_penalty: options.joker_penalty,
+            total_cards: hand_mask.count_ones() as u8,
+            target_cards: options.target_cards,
+            hand_jokers: options.enumeration.deck.jokers_in(hand_mask),
+            hand_mask,
+            deck: options.enumeration.deck,
+            scoring: options.enumeration.scoring,
+            ace_mode: options.enumeration.ace_mode,
+            weights: options.weights,
+        }
+    }
+
+    /// `CoverOptions::joker_penalty` for each joker a selection spending
+    /// `used_jokers` leaves unmelded.
+    fn joker_charge(&self, used_jokers: u8) -> i32 {
+        self.hand_jokers.saturating_sub(used_jokers) as i32 * self.joker_penalty
+    }
+
+    /// Score of a selection covering `current_mask`.
+    fn score(&self, current_mask: u128, total_points: i32, used_jokers: u8) -> Score {
+        let covered_cards = current_mask.count_ones() as u8;
+        let score = Score {
+            weighted: self.weights.score(covered_cards, total_points, used_jokers),
+            ..Score::new(
+                covered_cards,
+                total_points,
+                used_jokers,
+                self.total_cards,
+                self.target_cards,
+                self.threshold,
+                self.min_first_points,
+                self.joker_charge(used_jokers),
+            )
+        };
+        let leftover = self.hand_mask & !current_mask;
+        match self.objective {
+            OBJ_MIN_DEADWOOD_SOFT => Score {
+                near_melds: near_melds(leftover, &self.deck, self.ace_mode),
+                ..score
+            },
+            OBJ_MAX_CARDS_SAFE => Score {
+                max_leftover: max_leftover(leftover, &self.deck, &self.scoring, self.joker_penalty),
+                ..score
+            },
+            _ => score,
+        }
+    }
+}
+
+/// Read-only inputs shared by every node of the cover search.
+struct SearchContext<'a> {
+    masks: &'a [u128],
+    points: &'a [i32],
+    jokers_used: &'a [u8],
+    /// Union of the meld masks from each index to the end of the list.
+    suffix_masks: Vec<u128>,
+    /// Sum of the meld points from each index to the end of the list.
+    suffix_points: Vec<i32>,
+    scorer: Scorer,
+    required_mask: u128,
+    max_melds: usize,
+    max_jokers: Option<u8>,
+    deadline: Option<Instant>,
+    nodes: AtomicU32,
+    timed_out: AtomicBool,
+    on_progress: Option<ProgressCallback>,
+    /// When `on_progress` was last called.
+    last_progress: Mutex<Option<Instant>>,
+}
+
+impl<'a> SearchContext<'a> {
+    fn new(
+        masks: &'a [u128],
+        points: &'a [i32],
+        jokers_used: &'a [u8],
+        objective: u8,
+        threshold: i32,
+        hand_mask: u128,
+        options: &CoverOptions,
+    ) -> Self {
+        let mut suffix_masks = vec![0u128; masks.len() + 1];
+        let mut suffix_points = vec![0i32; masks.len() + 1];
+        for idx in (0..masks.len()).rev() {
+            suffix_masks[idx] = suffix_masks[idx + 1] | masks[idx];
+            suffix_points[idx] = suffix_points[idx + 1].saturating_add(points[idx].max(0));
+        }
+        SearchContext {
+            masks,
+            points,
+            jokers_used,
+            suffix_masks,
+            suffix_points,
+            scorer: Scorer::new(objective, threshold, hand_mask, options),
+            required_mask: options.required_mask,
+            max_melds: options.max_melds,
+            max_jokers: options.max_jokers,
+            deadline: options.time_budget.map(|budget| Instant::now() + budget),
+            nodes: AtomicU32::new(0),
+            timed_out: AtomicBool::new(false),
+            on_progress: options.on_progress.clone(),
+            last_progress: Mutex::new(None),
+        }
+    }
+
+    /// Passes a new best score to `on_progress` unless it was called less
+    /// than `PROGRESS_INTERVAL` ago.
+    fn report_progress(&self, score: &Score) {
+        let Some(on_progress) = &self.on_progress else {
+            return;
+        };
+        let now = Instant::now();
+        {
+            let mut last = self
+                .last_progress
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if last.is_some_and(|last| now - last < PROGRESS_INTERVAL) {
+                return;
+            }
+            *last = Some(now);
+        }
+        on_progress(score.covered_cards, score.total_points);
+    }
+
+    /// Whether the time budget has run out. The clock is only read every
+    /// `DEADLINE_CHECK_INTERVAL` nodes to keep the hot path cheap.
+    fn out_of_time(&self) -> bool {
+        let Some(deadline) = self.deadline else {
+            return false;
+        };
+        if self.timed_out.load(AtomicOrdering::Relaxed) {
+            return true;
+        }
+        let visited = self.nodes.fetch_add(1, AtomicOrdering::Relaxed);
+        if visited.is_multiple_of(DEADLINE_CHECK_INTERVAL) && Instant::now() >= deadline {
+            self.timed_out.store(true, AtomicOrdering::Relaxed);
+            return true;
+        }
+        false
+    }
+
+    /// Score of a selection covering `current_mask`.
+    fn score(&self, current_mask: u128, total_points: i32, used_jokers: u8) -> Score {
+        self.scorer.score(current_mask, total_points, used_jokers)
+    }
+
+    /// Optimistic score for any selection below this node.
+    ///
+    /// The bound assumes every remaining meld card gets covered and every
+    /// remaining meld's points get added without spending another joker, while
+    /// no joker is charged as left in hand and the leftover cards count as
+    /// every near-meld there could be and as worth nothing. That score is at
+    /// least as good as any reachable one in each `Score` field, and every
+    /// objective orders on those fields monotonically, so a subtree can be pruned whenever its
+    /// bound does not beat the score it has to improve on.
+    fn bound(
+        &self,
+        idx: usize,
+        current_mask: u128,
+        current_points: i32,
+        current_jokers: u8,
+    ) -> Score {
+        let reachable = (current_mask | self.suffix_masks[idx]).count_ones() as u8;
+        let points = current_points.saturating_add(self.suffix_points[idx]);
+        let scorer = &self.scorer;
+        Score {
+            joker_penalty: 0,
+            near_melds: u16::MAX,
+            weighted: scorer.weights.score(reachable, points, current_jokers),
+            ..Score::new(
+                reachable,
+                points,
+                current_jokers,
+                scorer.total_cards,
+                scorer.target_cards,
+                scorer.threshold,
+                scorer.min_first_points,
+                0,
+            )
+        }
+    }
+
+    /// Whether a selection covering `current_mask` meets the required cards.
+    fn covers_required(&self, current_mask: u128) -> bool {
+        current_mask & self.required_mask == self.required_mask
+    }
+
+    /// Whether meld `idx` may join a selection of `selected` melds covering
+    /// `current_mask` with `current_jokers` jokers: it must not overlap them,
+    /// and must keep within the meld cap and the joker budget.
+    fn can_take(
+        &self,
+        idx: usize,
+        current_mask: u128,
+        selected: usize,
+        current_jokers: u8,
+    ) -> bool {
+        current_mask & self.masks[idx] == 0
+            && (self.max_melds == 0 || selected < self.max_melds)
+            && self
+                .max_jokers
+                .is_none_or(|max_jokers| current_jokers + self.jokers_used[idx] <= max_jokers)
+    }
+
+    /// Masks of the selected melds in ascending order, the layout that breaks
+    /// ties between equally scored covers.
+    fn layout(&self, selection: &[usize]) -> Vec<u128> {
+        let mut layout: Vec<u128> = selection.iter().map(|&idx| self.masks[idx]).collect();
+        layout.sort_unstable();
+        layout
+    }
+
+    /// Whether a selection scoring `score` beats `best`. Covers equal under
+    /// every objective criterion fall back to the lexicographically smallest
+    /// layout, so the winner never depends on the order the melds are tried.
+    fn beats(&self, score: &Score, selection: &[usize], best: &Candidate) -> bool {
+        let (best_score, best_selection, _, _, _) = best;
+        if better_score(self.scorer.objective, score, best_score) {
+            return true;
+        }
+        !better_score(self.scorer.objective, best_score, score)
+            && self.layout(selection) < self.layout(best_selection)
+    }
+
+    /// Whether any selection below this node could still beat `best`, or tie
+    /// it with a smaller layout.
+    fn can_improve(
+        &self,
+        idx: usize,
+        current_mask: u128,
+        current_points: i32,
+        current_jokers: u8,
+        best: &Best,
+    ) -> bool {
+        if !self.covers_required(current_mask | self.suffix_masks[idx]) {
+            return false;
+        }
+        let Some((best_score, _, _, _, _)) = best else {
+            return true;
+        };
+        let bound = self.bound(idx, current_mask, current_points, current_jokers);
+        !better_score(self.scorer.objective, best_score, &bound)
+    }
+}
+
+fn update_best(
+    ctx: &SearchContext,
     current_mask: u128,
     current_points: i32,
     current_jokers: u8,
     selection: &[usize],
-    best: &mut Option<(Score, Vec<usize>, i32, u8, u128)>,
+    best: &mut Best,
 ) {
-    let covered_cards = current_mask.count_ones() as u8;
-    let deadwood = total_cards.saturating_sub(covered_cards);
-    let score = Score {
-        meets_threshold: current_points >= threshold,
-        target_met: covered_cards >= 14,
-        covered_cards,
-        deadwood,
-        total_points: current_points,
-        used_jokers: current_jokers,
-    };
+    if !ctx.covers_required(current_mask) {
+        return;
+    }
+    let score = ctx.score(current_mask, current_points, current_jokers);
 
-    match best {
-        None => {
-            *best = Some((score, selection.to_vec(), current_points, current_jokers, current_mask));
+    if best
+        .as_ref()
+        .is_none_or(|best| ctx.beats(&score, selection, best))
+    {
+        ctx.report_progress(&score);
+        *best = Some((
+            score,
+            selection.to_vec(),
+            current_points,
+            current_jokers,
+            current_mask,
+        ));
+    }
+}
+
+/// Points, jokers spent and points net of the joker penalty of a path.
+type MemoState = (i32, u8, i64);
+
+/// Most nodes a `Memo` holds; past that the search goes on without storing
+/// more, so no hand can make it allocate without bound.
+const MEMO_LIMIT: usize = 1 << 18;
+
+/// State of the latest undominated path to reach each `(idx, current_mask)`
+/// node.
+///
+/// The melds still on offer below a node depend only on that key, and every
+/// objective prefers more points (also net of any joker penalty) and fewer
+/// jokers at equal coverage, so a later path arriving no better on all three
+/// and strictly worse on points or jokers can only find worse covers than the
+/// earlier one did.
+/// An exact tie is still searched, since its layout may win the tie-break.
+/// Only nodes that pass `SearchContext::can_improve` are stored.
+struct Memo {
+    seen: HashMap<(usize, u128), MemoState>,
+    limit: usize,
+}
+
+impl Memo {
+    fn new() -> Self {
+        Memo::with_limit(MEMO_LIMIT)
+    }
+
+    fn with_limit(limit: usize) -> Self {
+        Memo {
+            seen: HashMap::new(),
+            limit,
         }
-        Some((best_score, _, _, _, _)) => {
-            if better_score(objective, &score, best_score) {
-                *best = Some((score, selection.to_vec(), current_points, current_jokers, current_mask));
+    }
+
+    /// Whether an earlier visit of `node` makes this one redundant; if not,
+    /// this visit's `state` is recorded for the next.
+    fn redundant(&mut self, node: (usize, u128), state: MemoState) -> bool {
+        if let Some(seen) = self.seen.get_mut(&node) {
+            if dominates(seen, &state) {
+                return true;
             }
+            *seen = state;
+        } else if self.seen.len() < self.limit {
+            self.seen.insert(node, state);
         }
+        false
     }
 }
 
+/// Whether an earlier visit of a node with `seen` makes `current` redundant.
+fn dominates(seen: &MemoState, current: &MemoState) -> bool {
+    let (seen_points, seen_jokers, seen_net) = *seen;
+    let (points, jokers, net) = *current;
+    let no_worse = seen_points >= points && seen_jokers <= jokers && seen_net >= net;
+    no_worse && (seen_points, seen_jokers) != (points, jokers)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn search_best_cover(
+    ctx: &SearchContext,
     idx: usize,
     current_mask: u128,
     current_points: i32,
     current_jokers: u8,
     selection: &mut Vec<usize>,
-    masks: &[u128],
-    points: &[i32],
-    jokers_used: &[u8],
-    objective: u8,
-    threshold: i32,
-    total_cards: u8,
-    best: &mut Option<(Score, Vec<usize>, i32, u8, u128)>,
+    best: &mut Best,
+    memo: &mut Memo,
 ) {
     update_best(
-        objective,
-        threshold,
-        total_cards,
+        ctx,
         current_mask,
         current_points,
         current_jokers,
@@ -141,96 +719,1531 @@ fn search_best_cover(
         best,
     );
 
-    if idx == masks.len() {
+    if idx == ctx.masks.len() || ctx.out_of_time() {
         return;
     }
+    if !ctx.can_improve(idx, current_mask, current_points, current_jokers, best) {
+        return;
+    }
+    // A meld cap makes the number of melds taken part of the state, which the
+    // memo key leaves out.
+    if ctx.max_melds == 0 {
+        let net = current_points as i64 - ctx.scorer.joker_charge(current_jokers) as i64;
+        if memo.redundant((idx, current_mask), (current_points, current_jokers, net)) {
+            return;
+        }
+    }
 
     // Skip current meld.
     search_best_cover(
+        ctx,
         idx + 1,
         current_mask,
         current_points,
         current_jokers,
         selection,
-        masks,
-        points,
-        jokers_used,
-        objective,
-        threshold,
-        total_cards,
         best,
+        memo,
     );
 
-    let meld_mask = masks[idx];
-    if current_mask & meld_mask != 0 {
+    let meld_mask = ctx.masks[idx];
+    if !ctx.can_take(idx, current_mask, selection.len(), current_jokers) {
         return;
     }
 
     selection.push(idx);
     search_best_cover(
+        ctx,
         idx + 1,
         current_mask | meld_mask,
-        current_points + points[idx],
-        current_jokers + jokers_used[idx],
+        current_points.saturating_add(ctx.points[idx]),
+        current_jokers + ctx.jokers_used[idx],
         selection,
-        masks,
-        points,
-        jokers_used,
-        objective,
-        threshold,
-        total_cards,
         best,
+        memo,
     );
     selection.pop();
 }
 
-pub fn best_cover(mask_hi: u64, mask_lo: u64, objective: u8, threshold: i32) -> CoverResult {
-    let melds = enumerate_melds(mask_hi, mask_lo);
-    if melds.is_empty() {
-        return CoverResult {
-            melds,
-            covered_cards: 0,
-            total_points: 0,
-            used_jokers: 0,
+/// Most hand cards `search_dp` will index its table by, for a table of
+/// 2^16 entries.
+const DP_MAX_CARDS: u32 = 16;
+
+/// Best selection by dynamic programming over the subsets of hand cards
+/// covered rather than over the melds taken. The cards a selection covers
+/// fix the jokers it spends, and every objective prefers more points at
+/// equal cards and jokers, so each subset only keeps the selection covering
+/// exactly it for the most points, ties going to the smaller layout as in
+/// `SearchContext::beats`. Hands over `DP_MAX_CARDS` cards, and meld caps,
+/// which make the number of melds taken part of the state, are searched by
+/// `search_from_root` instead.
+fn search_dp(ctx: &SearchContext) -> Best {
+    let cards = ctx.scorer.hand_mask.count_ones();
+    if cards > DP_MAX_CARDS || ctx.max_melds != 0 {
+        return search_from_root(ctx);
+    }
+    // Bit `pos` of a subset stands for the hand's `pos`-th lowest card.
+    let positions = mask_cards(ctx.scorer.hand_mask);
+    let subset_of = |mask: u128| {
+        positions
+            .iter()
+            .enumerate()
+            .filter(|&(_, &card_id)| mask & card_bitmask(card_id) != 0)
+            .fold(0usize, |subset, (pos, _)| subset | 1 << pos)
+    };
+    let meld_subsets: Vec<usize> = ctx.masks.iter().map(|&mask| subset_of(mask)).collect();
+    // Points and jokers of the best selection covering each subset, with the
+    // meld it took last.
+    let mut table: Vec<Option<(i32, u8, usize)>> = vec![None; 1 << cards];
+    table[0] = Some((0, 0, usize::MAX));
+    let selection_of = |table: &[Option<(i32, u8, usize)>], mut subset: usize| {
+        let mut selection = Vec::new();
+        while subset != 0 {
+            let (_, _, idx) = table[subset].expect("a covered subset was reached");
+            selection.push(idx);
+            subset &= !meld_subsets[idx];
+        }
+        selection.sort_unstable();
+        selection
+    };
+    // A meld only ever adds cards, so every subset is final before any
+    // larger one is extended.
+    for subset in 0..table.len() {
+        let Some((points, jokers, _)) = table[subset] else {
+            continue;
         };
+        for (idx, &meld) in meld_subsets.iter().enumerate() {
+            let over_jokers = ctx
+                .max_jokers
+                .is_some_and(|max_jokers| jokers + ctx.jokers_used[idx] > max_jokers);
+            if subset & meld != 0 || over_jokers {
+                continue;
+            }
+            let next = subset | meld;
+            let next_points = points.saturating_add(ctx.points[idx]);
+            let replace = match table[next] {
+                None => true,
+                Some((best_points, ..)) if best_points != next_points => next_points > best_points,
+                Some(_) => {
+                    let mut selection = selection_of(&table, subset);
+                    selection.push(idx);
+                    ctx.layout(&selection) < ctx.layout(&selection_of(&table, next))
+                }
+            };
+            if replace {
+                table[next] = Some((next_points, jokers + ctx.jokers_used[idx], idx));
+            }
+        }
     }
 
-    let masks: Vec<u128> = melds
-        .iter()
-        .map(|meld| merge_words(meld.mask_hi, meld.mask_lo))
-        .collect();
-    let points: Vec<i32> = melds.iter().map(|meld| meld.points).collect();
-    let jokers_used: Vec<u8> = melds.iter().map(|meld| meld.jokers_used).collect();
+    let mut best: Best = None;
+    for (subset, entry) in table.iter().enumerate() {
+        let Some((points, jokers, _)) = *entry else {
+            continue;
+        };
+        let mask = positions
+            .iter()
+            .enumerate()
+            .filter(|&(pos, _)| subset & 1 << pos != 0)
+            .fold(0u128, |mask, (_, &card_id)| mask | card_bitmask(card_id));
+        if !ctx.covers_required(mask) {
+            continue;
+        }
+        let score = ctx.score(mask, points, jokers);
+        if best
+            .as_ref()
+            .is_some_and(|(best_score, ..)| better_score(ctx.scorer.objective, best_score, &score))
+        {
+            continue;
+        }
+        let selection = selection_of(&table, subset);
+        if best
+            .as_ref()
+            .is_none_or(|best| ctx.beats(&score, &selection, best))
+        {
+            best = Some((score, selection, points, jokers, mask));
+        }
+    }
+    best
+}
 
-    let total_cards = merge_words(mask_hi, mask_lo).count_ones() as u8;
+/// Levels of the skip/include tree that `best_cover_parallel` forks into
+/// rayon tasks; below this depth each subtree is searched sequentially.
+const PARALLEL_SPLIT_DEPTH: usize = 3;
 
-    let mut best: Option<(Score, Vec<usize>, i32, u8, u128)> = None;
-    let mut selection = Vec::new();
-    search_best_cover(
-        0,
-        0,
-        0,
-        0,
-        &mut selection,
-        &masks,
-        &points,
-        &jokers_used,
-        objective,
-        threshold,
-        total_cards,
+fn merge_best(ctx: &SearchContext, best: &mut Best, candidate: Best) {
+    let Some(candidate) = candidate else {
+        return;
+    };
+    if best
+        .as_ref()
+        .is_none_or(|best| ctx.beats(&candidate.0, &candidate.1, best))
+    {
+        *best = Some(candidate);
+    }
+}
+
+/// Fork/join variant of `search_best_cover`.
+///
+/// The skip and include branches of the first few melds run under `rayon::join`,
+/// each seeded with the incumbent known at the fork. Equal scores are settled
+/// by layout, so merging the branches picks the sequential search's winner.
+#[allow(clippy::too_many_arguments)]
+fn search_parallel(
+    ctx: &SearchContext,
+    idx: usize,
+    current_mask: u128,
+    current_points: i32,
+    current_jokers: u8,
+    mut selection: Vec<usize>,
+    depth: usize,
+    mut best: Best,
+) -> Best {
+    if depth == 0 || idx == ctx.masks.len() {
+        search_best_cover(
+            ctx,
+            idx,
+            current_mask,
+            current_points,
+            current_jokers,
+            &mut selection,
+            &mut best,
+            &mut Memo::new(),
+        );
+        return best;
+    }
+
+    update_best(
+        ctx,
+        current_mask,
+        current_points,
+        current_jokers,
+        &selection,
         &mut best,
     );
+    if !ctx.can_improve(idx, current_mask, current_points, current_jokers, &best) {
+        return best;
+    }
 
-    let (score, indices, total_points, used_jokers, _) = best.unwrap();
-    let mut chosen_melds: Vec<Meld> = indices.into_iter().map(|idx| melds[idx].clone()).collect();
-    chosen_melds.sort_by(|a, b| {
-        (a.mask_hi, a.mask_lo, a.kind, a.jokers_used, a.points).cmp(&(b.mask_hi, b.mask_lo, b.kind, b.jokers_used, b.points))
-    });
+    let meld_mask = ctx.masks[idx];
+    let include_selection = ctx
+        .can_take(idx, current_mask, selection.len(), current_jokers)
+        .then(|| {
+            let mut include_selection = selection.clone();
+            include_selection.push(idx);
+            include_selection
+        });
+    let seed = best.clone();
+    let (skipped, included) = rayon::join(
+        || {
+            search_parallel(
+                ctx,
+                idx + 1,
+                current_mask,
+                current_points,
+                current_jokers,
+                selection,
+                depth - 1,
+                seed,
+            )
+        },
+        || {
+            include_selection.map(|include_selection| {
+                search_parallel(
+                    ctx,
+                    idx + 1,
+                    current_mask | meld_mask,
+                    current_points.saturating_add(ctx.points[idx]),
+                    current_jokers + ctx.jokers_used[idx],
+                    include_selection,
+                    depth - 1,
+                    best.clone(),
+                )
+            })
+        },
+    );
 
-    CoverResult {
-        melds: chosen_melds,
-        covered_cards: score.covered_cards,
-        total_points,
-        used_jokers,
+    let mut best = skipped;
+    merge_best(ctx, &mut best, included.flatten());
+    best
+}
+
+type Candidate = (Score, Vec<usize>, i32, u8, u128);
+
+/// The `k` best selections seen so far, best first, none dominated by
+/// another: no kept selection covers a subset of another's cards while
+/// scoring no better.
+struct TopCovers {
+    k: usize,
+    entries: Vec<Candidate>,
+}
+
+impl TopCovers {
+    fn offer(&mut self, ctx: &SearchContext, candidate: Candidate) {
+        if self
+            .entries
+            .iter()
+            .any(|entry| covers_better(ctx, entry, &candidate))
+        {
+            return;
+        }
+        let kept = self.entries.len();
+        self.entries
+            .retain(|entry| !covers_better(ctx, &candidate, entry));
+        if self.entries.len() == kept && kept == self.k {
+            if !ctx.beats(&candidate.0, &candidate.1, &self.entries[self.k - 1]) {
+                return;
+            }
+            self.entries.pop();
+        }
+        let pos = self
+            .entries
+            .iter()
+            .position(|entry| ctx.beats(&candidate.0, &candidate.1, entry))
+            .unwrap_or(self.entries.len());
+        self.entries.insert(pos, candidate);
+    }
+
+    /// Whether a selection bounded by `bound` could still beat or tie the
+    /// worst kept one.
+    fn admits(&self, objective: u8, bound: &Score) -> bool {
+        match self.entries.get(self.k - 1) {
+            Some(worst) => !better_score(objective, &worst.0, bound),
+            None => true,
+        }
+    }
+}
+
+/// Whether `outer` makes `inner` redundant: it covers every card `inner`
+/// does and scores at least as well, with the `beats` layout deciding
+/// between equal scores over the same cards.
+fn covers_better(ctx: &SearchContext, outer: &Candidate, inner: &Candidate) -> bool {
+    let (outer_mask, inner_mask) = (outer.4, inner.4);
+    if outer_mask & inner_mask != inner_mask {
+        return false;
+    }
+    if outer_mask == inner_mask {
+        !ctx.beats(&inner.0, &inner.1, outer)
+    } else {
+        !better_score(ctx.scorer.objective, &inner.0, &outer.0)
+    }
+}
+
+fn search_top_covers(
+    ctx: &SearchContext,
+    idx: usize,
+    current_mask: u128,
+    current_points: i32,
+    current_jokers: u8,
+    selection: &mut Vec<usize>,
+    top: &mut TopCovers,
+) {
+    let score = ctx.score(current_mask, current_points, current_jokers);
+    top.offer(
+        ctx,
+        (
+            score,
+            selection.clone(),
+            current_points,
+            current_jokers,
+            current_mask,
+        ),
+    );
+
+    if idx == ctx.masks.len() || ctx.out_of_time() {
+        return;
+    }
+    let bound = ctx.bound(idx, current_mask, current_points, current_jokers);
+    if !top.admits(ctx.scorer.objective, &bound) {
+        return;
+    }
+
+    search_top_covers(
+        ctx,
+        idx + 1,
+        current_mask,
+        current_points,
+        current_jokers,
+        selection,
+        top,
+    );
+
+    let meld_mask = ctx.masks[idx];
+    if !ctx.can_take(idx, current_mask, selection.len(), current_jokers) {
+        return;
+    }
+
+    selection.push(idx);
+    search_top_covers(
+        ctx,
+        idx + 1,
+        current_mask | meld_mask,
+        current_points.saturating_add(ctx.points[idx]),
+        current_jokers + ctx.jokers_used[idx],
+        selection,
+        top,
+    );
+    selection.pop();
+}
+
+/// Collects, up to `limit`, the selections no further meld fits beside.
+#[allow(clippy::too_many_arguments)]
+fn search_maximal_covers(
+    ctx: &SearchContext,
+    idx: usize,
+    current_mask: u128,
+    current_points: i32,
+    current_jokers: u8,
+    selection: &mut Vec<usize>,
+    found: &mut Vec<Candidate>,
+    limit: usize,
+) {
+    if found.len() == limit || ctx.out_of_time() {
+        return;
+    }
+    if idx == ctx.masks.len() {
+        if ctx.masks.iter().all(|&mask| mask & current_mask != 0) {
+            found.push((
+                ctx.score(current_mask, current_points, current_jokers),
+                selection.clone(),
+                current_points,
+                current_jokers,
+                current_mask,
+            ));
+        }
+        return;
+    }
+
+    let meld_mask = ctx.masks[idx];
+    // Skipping a meld only leads to a maximal selection if something taken
+    // before or after it will overlap it.
+    if (current_mask | ctx.suffix_masks[idx + 1]) & meld_mask != 0 {
+        search_maximal_covers(
+            ctx,
+            idx + 1,
+            current_mask,
+            current_points,
+            current_jokers,
+            selection,
+            found,
+            limit,
+        );
+    }
+
+    if current_mask & meld_mask != 0 {
+        return;
+    }
+    selection.push(idx);
+    search_maximal_covers(
+        ctx,
+        idx + 1,
+        current_mask | meld_mask,
+        current_points.saturating_add(ctx.points[idx]),
+        current_jokers + ctx.jokers_used[idx],
+        selection,
+        found,
+        limit,
+    );
+    selection.pop();
+}
+
+/// The cover that melds nothing. Jokers never meld on their own, so this is
+/// also where a hand of nothing but jokers, or no cards at all, ends up.
+fn empty_cover(hand_mask: u128, threshold: i32, deck: &DeckConfig) -> CoverResult {
+    let card_count = hand_mask.count_ones() as u8;
+    CoverResult {
+        melds: Vec::new(),
+        covered_cards: 0,
+        total_points: 0,
+        used_jokers: 0,
+        deadwood: mask_cards(hand_mask),
+        deadwood_count: card_count,
+        meets_threshold: threshold <= 0,
+        target_met: false,
+        jokers_only: card_count > 0 && deck.jokers_in(hand_mask) == card_count,
+        empty_hand: card_count == 0,
+        timed_out: false,
+        infeasible: false,
+        truncated: false,
+        candidates: Vec::new(),
+    }
+}
+
+fn cover_result(
+    melds: &[Meld],
+    hand_mask: u128,
+    (score, indices, total_points, used_jokers, covered_mask): Candidate,
+    timed_out: bool,
+) -> CoverResult {
+    let mut chosen_melds: Vec<Meld> = indices.into_iter().map(|idx| melds[idx].clone()).collect();
+    chosen_melds.sort_by_key(Meld::sort_key);
+
+    CoverResult {
+        melds: chosen_melds,
+        covered_cards: score.covered_cards,
+        total_points,
+        used_jokers,
+        deadwood: mask_cards(hand_mask & !covered_mask),
+        deadwood_count: score.deadwood,
+        meets_threshold: score.meets_threshold,
+        target_met: score.target_met,
+        jokers_only: false,
+        empty_hand: false,
+        timed_out,
+        infeasible: false,
+        truncated: false,
+        candidates: Vec::new(),
+    }
+}
+
+/// Drops each meld some other meld makes redundant before the search sees it.
+///
+/// `M1` goes when some `M2` covers a superset of its cards for at least as
+/// many points and no more jokers, with its points still no lower once the
+/// `joker_penalty` for each joker it frees is charged, and no meld besides
+/// `M2` touches the cards `M2` adds. That last condition is what makes the
+/// swap safe: any cover using `M1` can take `M2` instead, since nothing else
+/// could have claimed the extra cards, and the result is at least as good
+/// under every objective. Without it a run such as 3-4-5-6 would hide the
+/// 3-4-5 that leaves the 6 free for a set. Of melds identical in mask, points
+/// and jokers, the first is kept.
+fn prune_dominated(melds: &[Meld], joker_penalty: i32) -> Vec<Meld> {
+    let masks: Vec<u128> = melds
+        .iter()
+        .map(|meld| merge_words(meld.mask_hi, meld.mask_lo))
+        .collect();
+    let mut meld_counts = [0u32; 128];
+    for &mask in &masks {
+        for card_id in mask_cards(mask) {
+            meld_counts[card_id as usize] += 1;
+        }
+    }
+    let dominates = |by: usize, idx: usize| {
+        let (mask, wider) = (masks[idx], masks[by]);
+        let (meld, other) = (&melds[idx], &melds[by]);
+        let freed_charge =
+            (meld.jokers_used as i64 - other.jokers_used as i64) * joker_penalty as i64;
+        if mask & !wider != 0
+            || other.points < meld.points
+            || other.jokers_used > meld.jokers_used
+            || (other.points as i64) - freed_charge < meld.points as i64
+        {
+            return false;
+        }
+        let identical =
+            mask == wider && other.points == meld.points && other.jokers_used == meld.jokers_used;
+        if identical {
+            return by < idx;
+        }
+        mask_cards(wider & !mask)
+            .into_iter()
+            .all(|card_id| meld_counts[card_id as usize] == 1)
+    };
+    (0..melds.len())
+        .filter(|&idx| !(0..melds.len()).any(|by| by != idx && dominates(by, idx)))
+        .map(|idx| melds[idx].clone())
+        .collect()
+}
+
+/// Enumerates the hand's melds and runs `search` over a context built from
+/// them, returning `None` when the hand holds no meld at all.
+fn with_search_context<T>(
+    mask_hi: u64,
+    mask_lo: u64,
+    objective: u8,
+    threshold: i32,
+    options: &CoverOptions,
+    prune: bool,
+    search: impl FnOnce(&[Meld], &SearchContext) -> T,
+) -> Option<T> {
+    with_candidates(
+        enumerate_melds(mask_hi, mask_lo, &options.enumeration),
+        merge_words(mask_hi, mask_lo),
+        objective,
+        threshold,
+        options,
+        prune,
+        search,
+    )
+}
+
+/// `with_search_context` over melds the caller already enumerated.
+fn with_candidates<T>(
+    melds: Vec<Meld>,
+    hand_mask: u128,
+    objective: u8,
+    threshold: i32,
+    options: &CoverOptions,
+    prune: bool,
+    search: impl FnOnce(&[Meld], &SearchContext) -> T,
+) -> Option<T> {
+    let melds = searchable_melds(melds, options, prune);
+    if melds.is_empty() {
+        return None;
+    }
+    Some(search_melds(
+        &melds, hand_mask, objective, threshold, options, search,
+    ))
+}
+
+/// The enumerated `melds` a cover under `options` may use, dominated ones
+/// dropped too when `prune` is set.
+fn searchable_melds(mut melds: Vec<Meld>, options: &CoverOptions, prune: bool) -> Vec<Meld> {
+    // Dropping these up front keeps the forbidden check out of the recursion.
+    // Partial melds are not melds at all, so no cover may use them.
+    melds.retain(|meld| {
+        meld.kind != KIND_PARTIAL
+            && merge_words(meld.mask_hi, meld.mask_lo) & options.forbidden_mask == 0
+            && (meld.is_pure || !options.pure_only)
+    });
+    // Only a single best cover can do without the dominated melds; ranking
+    // several covers still lists the alternatives they form.
+    if prune {
+        melds = prune_dominated(&melds, options.joker_penalty);
+    }
+    melds
+}
+
+/// The meld columns `best_cover_core` searches: masks, points and jokers.
+fn meld_columns(melds: &[Meld]) -> (Vec<u128>, Vec<i32>, Vec<u8>) {
+    let masks = melds
+        .iter()
+        .map(|meld| merge_words(meld.mask_hi, meld.mask_lo))
+        .collect();
+    let points = melds.iter().map(|meld| meld.points).collect();
+    let jokers_used = melds.iter().map(|meld| meld.jokers_used).collect();
+    (masks, points, jokers_used)
+}
+
+/// Runs `search` over a context built from `melds`, in the order given.
+fn search_melds<T>(
+    melds: &[Meld],
+    hand_mask: u128,
+    objective: u8,
+    threshold: i32,
+    options: &CoverOptions,
+    search: impl FnOnce(&[Meld], &SearchContext) -> T,
+) -> T {
+    let (masks, points, jokers_used) = meld_columns(melds);
+    let ctx = SearchContext::new(
+        &masks,
+        &points,
+        &jokers_used,
+        objective,
+        threshold,
+        hand_mask,
+        options,
+    );
+    search(melds, &ctx)
+}
+
+/// Runs `search` over the hand's melds and packages the winning selection as
+/// a `CoverResult`. A search that accepts no selection means the required
+/// cards cannot all be melded.
+fn solve(
+    mask_hi: u64,
+    mask_lo: u64,
+    objective: u8,
+    threshold: i32,
+    options: &CoverOptions,
+    search: impl FnOnce(&SearchContext) -> Best,
+) -> CoverResult {
+    let (melds, truncated) = enumerate_melds_capped(mask_hi, mask_lo, &options.enumeration);
+    CoverResult {
+        truncated,
+        ..solve_candidates(
+            melds,
+            merge_words(mask_hi, mask_lo),
+            objective,
+            threshold,
+            options,
+            search,
+        )
+    }
+}
+
+/// `solve` over melds the caller already enumerated.
+fn solve_candidates(
+    melds: Vec<Meld>,
+    hand_mask: u128,
+    objective: u8,
+    threshold: i32,
+    options: &CoverOptions,
+    search: impl FnOnce(&SearchContext) -> Best,
+) -> CoverResult {
+    with_candidates(
+        melds,
+        hand_mask,
+        objective,
+        threshold,
+        options,
+        true,
+        |melds, ctx| {
+            let best = search(ctx);
+            let timed_out = ctx.timed_out.load(AtomicOrdering::Relaxed);
+            match best {
+                Some(best) => cover_result(melds, hand_mask, best, timed_out),
+                None => CoverResult {
+                    timed_out,
+                    infeasible: true,
+                    ..empty_cover(hand_mask, threshold, &options.enumeration.deck)
+                },
+            }
+        },
+    )
+    .unwrap_or_else(|| CoverResult {
+        infeasible: options.required_mask != 0,
+        ..empty_cover(hand_mask, threshold, &options.enumeration.deck)
+    })
+}
+
+pub fn best_cover_with(
+    mask_hi: u64,
+    mask_lo: u64,
+    objective: u8,
+    threshold: i32,
+    options: &CoverOptions,
+) -> CoverResult {
+    let (melds, truncated) = enumerate_melds_capped(mask_hi, mask_lo, &options.enumeration);
+    let hand_mask = merge_words(mask_hi, mask_lo);
+    let deck = &options.enumeration.deck;
+    let melds = searchable_melds(melds, options, true);
+    if melds.is_empty() {
+        return CoverResult {
+            truncated,
+            infeasible: options.required_mask != 0,
+            ..empty_cover(hand_mask, threshold, deck)
+        };
+    }
+    let (masks, points, jokers_used) = meld_columns(&melds);
+    let core = best_cover_core(
+        &masks,
+        &points,
+        &jokers_used,
+        objective,
+        threshold,
+        hand_mask,
+        options,
+    );
+    if core.infeasible {
+        return CoverResult {
+            truncated,
+            timed_out: core.timed_out,
+            infeasible: true,
+            ..empty_cover(hand_mask, threshold, deck)
+        };
+    }
+    let covered_mask = core
+        .selection
+        .iter()
+        .fold(0u128, |mask, &idx| mask | masks[idx]);
+    let score = Scorer::new(objective, threshold, hand_mask, options).score(
+        covered_mask,
+        core.total_points,
+        core.used_jokers,
+    );
+    let best = (
+        score,
+        core.selection,
+        core.total_points,
+        core.used_jokers,
+        covered_mask,
+    );
+    CoverResult {
+        truncated,
+        ..cover_result(&melds, hand_mask, best, core.timed_out)
+    }
+}
+
+/// What `best_cover_core` chose.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CoreCover {
+    /// Indices of the chosen melds, ascending.
+    pub selection: Vec<usize>,
+    pub total_points: i32,
+    pub used_jokers: u8,
+    /// No selection melds every card of `CoverOptions::required_mask`, so
+    /// nothing was chosen.
+    pub infeasible: bool,
+    /// The `CoverOptions::time_budget` ran out before the search finished.
+    pub timed_out: bool,
+}
+
+/// The search `best_cover_with` runs once it has enumerated and pruned the
+/// hand's melds, over bare meld columns so benchmarks and fuzzing can drive
+/// it outside Python: meld `i` covers `masks[i]` for `points[i]` points using
+/// `jokers[i]` jokers, the three slices being of equal length. `hand_mask`
+/// is the whole hand the melds come from, cards no meld covers included,
+/// which the deadwood, leftover tie-breaks and joker penalty count. The melds
+/// are searched as given, so none is pruned here.
+pub fn best_cover_core(
+    masks: &[u128],
+    points: &[i32],
+    jokers: &[u8],
+    objective: u8,
+    threshold: i32,
+    hand_mask: u128,
+    options: &CoverOptions,
+) -> CoreCover {
+    let ctx = SearchContext::new(
+        masks, points, jokers, objective, threshold, hand_mask, options,
+    );
+    let best = search_from_root(&ctx);
+    let timed_out = ctx.timed_out.load(AtomicOrdering::Relaxed);
+    match best {
+        Some((_, selection, total_points, used_jokers, _)) => CoreCover {
+            selection,
+            total_points,
+            used_jokers,
+            infeasible: false,
+            timed_out,
+        },
+        None => CoreCover {
+            infeasible: true,
+            timed_out,
+            ..Default::default()
+        },
+    }
+}
+
+/// `best_cover` for a hand that differs from the one `prev` covered in
+/// `changed_id` alone, the card drawn or discarded since. When `prev` came
+/// from this function too, its candidate melds are reused and only those the
+/// card can touch are enumerated again; the search itself reruns in full.
+/// The result keeps its candidates for the next call.
+pub fn best_cover_delta(
+    prev: &CoverResult,
+    mask_hi: u64,
+    mask_lo: u64,
+    changed_id: u8,
+    objective: u8,
+    threshold: i32,
+) -> CoverResult {
+    let options = CoverOptions::default();
+    // A result from any other search kept no candidates to build on, and a
+    // truncated one kept too few.
+    let (candidates, truncated) = if prev.candidates.is_empty() || prev.truncated {
+        enumerate_melds_capped(mask_hi, mask_lo, &options.enumeration)
+    } else {
+        let candidates = melds_after_change(
+            &prev.candidates,
+            mask_hi,
+            mask_lo,
+            changed_id,
+            &options.enumeration,
+        );
+        (candidates, false)
+    };
+    let hand_mask = merge_words(mask_hi, mask_lo);
+    CoverResult {
+        candidates: candidates.clone(),
+        truncated,
+        ..solve_candidates(
+            candidates,
+            hand_mask,
+            objective,
+            threshold,
+            &options,
+            search_from_root,
+        )
+    }
+}
+
+/// Sequential search of the whole tree.
+fn search_from_root(ctx: &SearchContext) -> Best {
+    let mut best: Best = None;
+    let mut selection = Vec::new();
+    let mut memo = Memo::new();
+    search_best_cover(ctx, 0, 0, 0, 0, &mut selection, &mut best, &mut memo);
+    best
+}
+
+/// Fewest cards any cover spending at most `max_jokers` jokers leaves out.
+///
+/// Runs the `OBJ_MIN_DEADWOOD` search, bound pruning included, but only reads
+/// the winning score, so no `Meld` of the cover is ever copied out.
+pub fn min_deadwood(mask_hi: u64, mask_lo: u64, max_jokers: u8) -> u8 {
+    let total_cards = merge_words(mask_hi, mask_lo).count_ones() as u8;
+    let options = CoverOptions {
+        max_jokers: Some(max_jokers),
+        ..Default::default()
+    };
+    // The lowest threshold makes every cover meet it, leaving deadwood first.
+    with_search_context(
+        mask_hi,
+        mask_lo,
+        OBJ_MIN_DEADWOOD,
+        i32::MIN,
+        &options,
+        true,
+        |_, ctx| search_from_root(ctx).map_or(total_cards, |(score, ..)| score.deadwood),
+    )
+    .unwrap_or(total_cards)
+}
+
+/// A cover melding every card of the hand but at most one, the discard a
+/// player goes out on; `None` when two or more cards would be left over.
+pub fn go_out_layout(mask_hi: u64, mask_lo: u64) -> Option<CoverResult> {
+    let cover = best_cover_with(mask_hi, mask_lo, OBJ_MAX_CARDS, 0, &CoverOptions::default());
+    (cover.deadwood.len() <= 1).then_some(cover)
+}
+
+/// Best cover under `OBJ_WEIGHTED`, scoring each selection as
+/// `card_weight * covered - joker_weight * jokers + point_weight * points`.
+/// No weight may be negative.
+pub fn best_cover_weighted(
+    mask_hi: u64,
+    mask_lo: u64,
+    card_weight: f64,
+    point_weight: f64,
+    joker_weight: f64,
+) -> CoverResult {
+    let options = CoverOptions {
+        weights: CoverWeights {
+            card: card_weight,
+            point: point_weight,
+            joker: joker_weight,
+        },
+        ..Default::default()
+    };
+    best_cover_with(mask_hi, mask_lo, OBJ_WEIGHTED, 0, &options)
+}
+
+/// Best cover laying down natural melds only: every joker and wild card of
+/// the hand stays in it, even when spending one would meld more cards.
+pub fn best_cover_pure(mask_hi: u64, mask_lo: u64, objective: u8, threshold: i32) -> CoverResult {
+    let options = CoverOptions {
+        pure_only: true,
+        ..Default::default()
+    };
+    best_cover_with(mask_hi, mask_lo, objective, threshold, &options)
+}
+
+/// Whether the hand can go out: see `go_out_layout`.
+pub fn can_go_out(mask_hi: u64, mask_lo: u64) -> bool {
+    go_out_layout(mask_hi, mask_lo).is_some()
+}
+
+/// The meld that scores best under `objective` on its own, for laying one
+/// meld, say towards the opening threshold, without searching for a whole
+/// cover. Melds scoring alike go to the one with fewer jokers, then the one
+/// with the lower card IDs. Returns `None` when the hand holds no meld.
+pub fn best_single_meld(mask_hi: u64, mask_lo: u64, objective: u8) -> Option<Meld> {
+    let options = CoverOptions::default();
+    let scorer = Scorer::new(objective, 0, merge_words(mask_hi, mask_lo), &options);
+    let mut best: Option<(Score, Vec<u8>, Meld)> = None;
+    for meld in enumerate_melds(mask_hi, mask_lo, &options.enumeration) {
+        let mask = merge_words(meld.mask_hi, meld.mask_lo);
+        let score = scorer.score(mask, meld.points, meld.jokers_used);
+        let cards = mask_cards(mask);
+        let replace = match &best {
+            None => true,
+            Some((best_score, best_cards, best_meld)) => {
+                better_score(objective, &score, best_score)
+                    || (!better_score(objective, best_score, &score)
+                        && (meld.jokers_used, &cards) < (best_meld.jokers_used, best_cards))
+            }
+        };
+        if replace {
+            best = Some((score, cards, meld));
+        }
+    }
+    best.map(|(_, _, meld)| meld)
+}
+
+/// How cover `a` ranks against cover `b` under `objective`, by the same
+/// order the search uses: 1 when `a` is better, -1 when `b` is and 0 when
+/// neither is. Each cover keeps its own `meets_threshold` and `target_met`;
+/// the rest of its score is rebuilt from its melds and deadwood under the
+/// default `CoverOptions`.
+pub fn compare_covers(a: &CoverResult, b: &CoverResult, objective: u8) -> i8 {
+    let (a, b) = (cover_score(a, objective), cover_score(b, objective));
+    if better_score(objective, &a, &b) {
+        1
+    } else if better_score(objective, &b, &a) {
+        -1
+    } else {
+        0
+    }
+}
+
+/// `Score` of a finished cover, for comparing covers after the search.
+fn cover_score(cover: &CoverResult, objective: u8) -> Score {
+    let covered = cover.melds.iter().fold(0u128, |mask, meld| {
+        mask | merge_words(meld.mask_hi, meld.mask_lo)
+    });
+    let deadwood = cover
+        .deadwood
+        .iter()
+        .fold(0u128, |mask, &card_id| mask | card_bitmask(card_id));
+    let scorer = Scorer::new(objective, 0, covered | deadwood, &CoverOptions::default());
+    Score {
+        meets_threshold: cover.meets_threshold,
+        target_met: cover.target_met,
+        ..scorer.score(covered, cover.total_points, cover.used_jokers)
+    }
+}
+
+/// The discard leaving the best cover under `objective`, with that cover.
+///
+/// Every card is tried in turn; among equally good discards the one worth the
+/// most points goes, then the lowest ID. Jokers are only discarded from a hand
+/// holding nothing else. Returns `None` for an empty hand.
+pub fn best_discard(
+    mask_hi: u64,
+    mask_lo: u64,
+    objective: u8,
+    threshold: i32,
+    options: &CoverOptions,
+) -> Option<(u8, CoverResult)> {
+    let hand_mask = merge_words(mask_hi, mask_lo);
+    let deck = &options.enumeration.deck;
+    let cards = mask_cards(hand_mask);
+    let naturals: Vec<u8> = cards
+        .iter()
+        .copied()
+        .filter(|&card_id| card_id < deck.first_joker_id())
+        .collect();
+    let discards = if naturals.is_empty() { cards } else { naturals };
+
+    let mut best: Option<(Score, i32, u8, CoverResult)> = None;
+    for discard in discards {
+        let rest = hand_mask & !card_bitmask(discard);
+        let (rest_hi, rest_lo) = combine_mask(rest);
+        let cover = best_cover_with(rest_hi, rest_lo, objective, threshold, options);
+        let covered = cover
+            .deadwood
+            .iter()
+            .fold(rest, |mask, &card_id| mask & !card_bitmask(card_id));
+        let scorer = Scorer::new(objective, threshold, rest, options);
+        let score = scorer.score(covered, cover.total_points, cover.used_jokers);
+        let discard_points = match decode_card(discard, deck).rank {
+            Some(rank) => options.enumeration.scoring.points_for_rank(rank),
+            None => 0,
+        };
+        let replace = match &best {
+            None => true,
+            Some((best_score, best_points, _, _)) => {
+                better_score(objective, &score, best_score)
+                    || (!better_score(objective, best_score, &score)
+                        && discard_points > *best_points)
+            }
+        };
+        if replace {
+            best = Some((score, discard_points, discard, cover));
+        }
+    }
+    best.map(|(_, _, discard, cover)| (discard, cover))
+}
+
+/// Risk `discard_safety` counts for each known opponent card at the
+/// discard's rank, whichever suit or deck, which the discard could join in a
+/// set.
+const SET_RISK: i32 = 2;
+/// Risk for each known opponent card of the discard's suit one rank away.
+const ADJACENT_RISK: i32 = 2;
+/// Risk for each known opponent card of the discard's suit two ranks away,
+/// a gap the discard would fill.
+const GAP_RISK: i32 = 1;
+/// Risk of discarding a joker, which any opponent meld can take.
+const JOKER_RISK: i32 = 10;
+
+/// How safe discarding `discard_id` is against the cards an opponent is
+/// known to hold, such as the discards they picked up: 0 when none of those
+/// connects with it, lower the more of them it would help meld. Each one of
+/// its rank counts `SET_RISK`, the other deck's copy of its face included,
+/// and each of its suit one or two ranks away in a run `ace_mode` allows
+/// `ADJACENT_RISK` or `GAP_RISK`, under the standard deck. A joker is always
+/// worth `JOKER_RISK`.
+pub fn discard_safety(
+    discard_id: u8,
+    opponent_discards_hi: u64,
+    opponent_discards_lo: u64,
+    ace_mode: u8,
+) -> i32 {
+    let deck = DeckConfig::STANDARD;
+    let discard = decode_card(discard_id, &deck);
+    let (Some(rank), Some(suit)) = (discard.rank, discard.suit) else {
+        return -JOKER_RISK;
+    };
+    let known = merge_words(opponent_discards_hi, opponent_discards_lo) & !card_bitmask(discard_id);
+    let risk: i32 = mask_cards(known)
+        .into_iter()
+        .map(|card_id| decode_card(card_id, &deck))
+        .filter_map(|card| Some((card.rank?, card.suit?)))
+        .map(|(other_rank, other_suit)| {
+            if other_rank == rank {
+                return SET_RISK;
+            }
+            if other_suit != suit || !run_neighbours(rank, other_rank, ace_mode) {
+                return 0;
+            }
+            // Ranks apart, counting round through the king.
+            let apart = rank.abs_diff(other_rank);
+            if apart.min(NUM_RANKS as u8 - apart) == 1 {
+                ADJACENT_RISK
+            } else {
+                GAP_RISK
+            }
+        })
+        .sum();
+    -risk
+}
+
+/// Unseen draws ranked by expected improvement, best first, ties by ID.
+///
+/// A draw improves the hand by how many fewer cards the cover under
+/// `objective` leaves unmelded than if the drawn card stayed loose, so a draw
+/// that melds nothing scores 0. Copies of one face (and the jokers) are a
+/// single entry under its lowest unseen ID, weighted by the chance of drawing
+/// any of them: copies unseen over all cards unseen. Only draws that
+/// `melds_gained_by_draw` finds new melds for are searched again, over the
+/// hand's melds plus the gained ones.
+pub fn rank_draws(
+    hand_hi: u64,
+    hand_lo: u64,
+    unseen_hi: u64,
+    unseen_lo: u64,
+    objective: u8,
+) -> Vec<(u8, f64)> {
+    let options = CoverOptions::default();
+    let deck = &options.enumeration.deck;
+    let hand_mask = merge_words(hand_hi, hand_lo);
+    let unseen: Vec<u8> = mask_cards(merge_words(unseen_hi, unseen_lo) & !hand_mask)
+        .into_iter()
+        .filter(|&card_id| (card_id as usize) < deck.card_count())
+        .collect();
+    if unseen.is_empty() {
+        return Vec::new();
+    }
+
+    let deadwood_of = |melds: &[Meld], hand_mask: u128| -> i32 {
+        let melds = prune_dominated(melds, options.joker_penalty);
+        let deadwood = search_melds(&melds, hand_mask, objective, 0, &options, |_, ctx| {
+            search_from_root(ctx).map_or(ctx.scorer.total_cards, |(score, ..)| score.deadwood)
+        });
+        deadwood as i32
+    };
+    let base_melds = enumerate_melds(hand_hi, hand_lo, &options.enumeration);
+    let base_deadwood = deadwood_of(&base_melds, hand_mask);
+
+    let face = |card_id: u8| {
+        let card = decode_card(card_id, deck);
+        (card.rank, card.suit)
+    };
+    // Each face's lowest unseen ID, with how many of its copies are unseen.
+    let mut faces: Vec<(u8, usize)> = Vec::new();
+    for &card_id in &unseen {
+        match faces
+            .iter_mut()
+            .find(|(first, _)| face(*first) == face(card_id))
+        {
+            Some((_, copies)) => *copies += 1,
+            None => faces.push((card_id, 1)),
+        }
+    }
+    let candidates: Vec<u8> = faces.iter().map(|&(card_id, _)| card_id).collect();
+    let gained = melds_gained_by_draw(hand_hi, hand_lo, &candidates, &options.enumeration);
+
+    let mut ranked: Vec<(u8, f64)> = gained
+        .into_iter()
+        .zip(&faces)
+        .map(|((card_id, gained), &(_, copies))| {
+            let improvement = if gained.is_empty() {
+                0
+            } else {
+                let mut melds = base_melds.clone();
+                melds.extend(gained);
+                base_deadwood + 1 - deadwood_of(&melds, hand_mask | card_bitmask(card_id))
+            };
+            let probability = copies as f64 / unseen.len() as f64;
+            (card_id, improvement as f64 * probability)
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    ranked
+}
+
+pub fn best_cover_parallel(
+    mask_hi: u64,
+    mask_lo: u64,
+    objective: u8,
+    threshold: i32,
+) -> CoverResult {
+    let options = CoverOptions::default();
+    solve(mask_hi, mask_lo, objective, threshold, &options, |ctx| {
+        search_parallel(ctx, 0, 0, 0, 0, Vec::new(), PARALLEL_SPLIT_DEPTH, None)
+    })
+}
+
+/// `best_cover` by dynamic programming over the subsets of hand cards, which
+/// for hands of up to 16 cards takes time in the number of subsets rather
+/// than in the combinations of melds; larger hands fall back to the usual
+/// search. See `search_dp`.
+pub fn best_cover_dp(mask_hi: u64, mask_lo: u64, objective: u8, threshold: i32) -> CoverResult {
+    let options = CoverOptions::default();
+    solve(mask_hi, mask_lo, objective, threshold, &options, search_dp)
+}
+
+/// Up to `k` of the best covers under `objective`, best first, leaving out
+/// any cover whose cards another returned cover includes at an equal or
+/// better score. Equal scores fall back to the layout `best_cover` breaks
+/// ties with.
+pub fn best_covers(
+    mask_hi: u64,
+    mask_lo: u64,
+    objective: u8,
+    threshold: i32,
+    k: usize,
+) -> Vec<CoverResult> {
+    if k == 0 {
+        return Vec::new();
+    }
+    let hand_mask = merge_words(mask_hi, mask_lo);
+    let options = CoverOptions::default();
+    let (melds, truncated) = enumerate_melds_capped(mask_hi, mask_lo, &options.enumeration);
+    with_candidates(
+        melds,
+        hand_mask,
+        objective,
+        threshold,
+        &options,
+        false,
+        |melds, ctx| {
+            let mut top = TopCovers {
+                k,
+                entries: Vec::with_capacity(k + 1),
+            };
+            let mut selection = Vec::new();
+            search_top_covers(ctx, 0, 0, 0, 0, &mut selection, &mut top);
+            top.entries
+                .into_iter()
+                .map(|candidate| CoverResult {
+                    truncated,
+                    ..cover_result(melds, hand_mask, candidate, false)
+                })
+                .collect()
+        },
+    )
+    .unwrap_or_else(|| {
+        vec![CoverResult {
+            truncated,
+            ..empty_cover(hand_mask, threshold, &options.enumeration.deck)
+        }]
+    })
+}
+
+/// Up to `limit` distinct maximal covers: selections of pairwise disjoint
+/// melds to which no other meld of the hand can be added. They are the first
+/// `limit` the search meets, then listed most cards first, then most points.
+pub fn all_maximal_covers(mask_hi: u64, mask_lo: u64, limit: usize) -> Vec<CoverResult> {
+    if limit == 0 {
+        return Vec::new();
+    }
+    let hand_mask = merge_words(mask_hi, mask_lo);
+    let options = CoverOptions::default();
+    let (melds, truncated) = enumerate_melds_capped(mask_hi, mask_lo, &options.enumeration);
+    with_candidates(
+        melds,
+        hand_mask,
+        OBJ_MAX_CARDS,
+        0,
+        &options,
+        false,
+        |melds, ctx| {
+            let mut found = Vec::new();
+            let mut selection = Vec::new();
+            search_maximal_covers(ctx, 0, 0, 0, 0, &mut selection, &mut found, limit);
+            found.sort_by_key(|(score, ..)| {
+                (Reverse(score.covered_cards), Reverse(score.total_points))
+            });
+            found
+                .into_iter()
+                .map(|candidate| CoverResult {
+                    truncated,
+                    ..cover_result(melds, hand_mask, candidate, false)
+                })
+                .collect()
+        },
+    )
+    .unwrap_or_else(|| {
+        vec![CoverResult {
+            truncated,
+            ..empty_cover(hand_mask, 0, &options.enumeration.deck)
+        }]
+    })
+}
+
+/// `best_cover` for each hand, solved across the rayon pool; results follow
+/// the order of `hands`.
+pub fn best_cover_batch(hands: &[(u64, u64)], objective: u8, threshold: i32) -> Vec<CoverResult> {
+    let options = CoverOptions::default();
+    hands
+        .par_iter()
+        .map(|&(mask_hi, mask_lo)| {
+            best_cover_with(mask_hi, mask_lo, objective, threshold, &options)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::notation::parse_hand;
+
+    const HANDS: [&[&str]; 4] = [
+        &["3S", "4S", "5S", "6S", "7S", "8S", "5H", "5D", "J1"],
+        &["7H", "8H", "9H", "7H#2", "8H#2", "9H#2", "QS", "QH", "QD"],
+        &["AS", "AH", "AD", "6C", "TC#2", "JC#2", "QC#2"],
+        &["2D", "3D", "4D", "2D#2", "3D#2", "4D#2", "J1", "J2"],
+    ];
+
+    #[test]
+    fn core_search_matches_the_hand_search() {
+        for hand in HANDS {
+            let hand_mask = parse_hand(hand).unwrap();
+            let (mask_hi, mask_lo) = combine_mask(hand_mask);
+            let melds = enumerate_melds(mask_hi, mask_lo, &EnumOptions::default());
+            let masks: Vec<u128> = melds
+                .iter()
+                .map(|meld| merge_words(meld.mask_hi, meld.mask_lo))
+                .collect();
+            let points: Vec<i32> = melds.iter().map(|meld| meld.points).collect();
+            let jokers: Vec<u8> = melds.iter().map(|meld| meld.jokers_used).collect();
+            let options = CoverOptions::default();
+            for objective in [OBJ_MAX_CARDS, OBJ_MIN_DEADWOOD, OBJ_MAX_POINTS] {
+                let expected = searched(&melds, hand_mask, objective);
+                let CoreCover {
+                    selection,
+                    total_points,
+                    used_jokers,
+                    ..
+                } = best_cover_core(&masks, &points, &jokers, objective, 0, hand_mask, &options);
+                let covered = selection.iter().fold(0u128, |mask, &idx| mask | masks[idx]);
+                assert_eq!(
+                    covered.count_ones() as u8,
+                    expected.covered_cards,
+                    "{hand:?}"
+                );
+                assert_eq!(total_points, expected.total_points, "{hand:?}");
+                assert_eq!(used_jokers, expected.used_jokers, "{hand:?}");
+            }
+        }
+    }
+
+    fn searched(melds: &[Meld], hand_mask: u128, objective: u8) -> CoverResult {
+        let options = CoverOptions::default();
+        search_melds(melds, hand_mask, objective, 0, &options, |melds, ctx| {
+            let best = search_from_root(ctx).expect("the empty cover always qualifies");
+            cover_result(melds, hand_mask, best, false)
+        })
+    }
+
+    #[test]
+    fn shuffled_meld_order_keeps_the_same_cover() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for hand in HANDS {
+            let hand_mask = parse_hand(hand).unwrap();
+            let (mask_hi, mask_lo) = combine_mask(hand_mask);
+            let mut melds = enumerate_melds(mask_hi, mask_lo, &EnumOptions::default());
+            for objective in [
+                OBJ_MAX_CARDS,
+                OBJ_MIN_DEADWOOD,
+                OBJ_FIRST_14,
+                OBJ_MAX_POINTS,
+            ] {
+                let expected = searched(&melds, hand_mask, objective);
+                for _ in 0..5 {
+                    melds.shuffle(&mut rng);
+                    let shuffled = searched(&melds, hand_mask, objective);
+                    let keys = |cover: &CoverResult| -> Vec<_> {
+                        cover.melds.iter().map(Meld::sort_key).collect()
+                    };
+                    assert_eq!(keys(&shuffled), keys(&expected), "{hand:?}");
+                    assert_eq!(shuffled.total_points, expected.total_points);
+                    assert_eq!(shuffled.deadwood, expected.deadwood);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn best_covers_leave_out_dominated_covers() {
+        let run_and_set = parse_hand(&["5S", "6S", "7S", "KH", "KD", "KC"]).unwrap();
+        let (mask_hi, mask_lo) = combine_mask(run_and_set);
+        let covers = best_covers(mask_hi, mask_lo, OBJ_MIN_DEADWOOD, 0, 4);
+        assert_eq!(covers.len(), 1);
+        assert_eq!(covers[0].deadwood_count, 0);
+        let union = |cover: &CoverResult| {
+            cover.melds.iter().fold(0u128, |mask, meld| {
+                mask | merge_words(meld.mask_hi, meld.mask_lo)
+            })
+        };
+        for hand in HANDS {
+            let (mask_hi, mask_lo) = combine_mask(parse_hand(hand).unwrap());
+            for objective in [OBJ_MAX_CARDS, OBJ_MIN_DEADWOOD, OBJ_MAX_POINTS] {
+                let covers = best_covers(mask_hi, mask_lo, objective, 0, 6);
+                assert!(!covers.is_empty(), "{hand:?}");
+                for (idx, cover) in covers.iter().enumerate() {
+                    for (other_idx, other) in covers.iter().enumerate() {
+                        assert!(
+                            idx == other_idx || union(cover) & !union(other) != 0,
+                            "{hand:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn partial_melds_never_join_a_cover() {
+        let mut options = CoverOptions::default();
+        options.enumeration.include_partials = true;
+        for hand in HANDS {
+            let (mask_hi, mask_lo) = combine_mask(parse_hand(hand).unwrap());
+            for objective in [OBJ_MAX_CARDS, OBJ_MIN_DEADWOOD, OBJ_MAX_POINTS] {
+                let with_partials = best_cover_with(mask_hi, mask_lo, objective, 0, &options);
+                let cover =
+                    best_cover_with(mask_hi, mask_lo, objective, 0, &CoverOptions::default());
+                let keys = |cover: &CoverResult| -> Vec<_> {
+                    cover.melds.iter().map(Meld::sort_key).collect()
+                };
+                assert_eq!(keys(&with_partials), keys(&cover), "{hand:?}");
+            }
+        }
+    }
+
+    /// The best score of any disjoint selection of `ctx`'s melds, found by
+    /// trying every subset.
+    fn brute_force_cover(ctx: &SearchContext) -> Option<Score> {
+        let mut best: Option<Score> = None;
+        for subset in 0u32..1 << ctx.masks.len() {
+            let (mut mask, mut points, mut jokers) = (0u128, 0i32, 0u8);
+            let mut disjoint = true;
+            for idx in (0..ctx.masks.len()).filter(|&idx| subset & 1 << idx != 0) {
+                disjoint &= mask & ctx.masks[idx] == 0;
+                mask |= ctx.masks[idx];
+                points += ctx.points[idx];
+                jokers += ctx.jokers_used[idx];
+            }
+            if !disjoint || !ctx.covers_required(mask) {
+                continue;
+            }
+            let score = ctx.score(mask, points, jokers);
+            if best
+                .as_ref()
+                .is_none_or(|best| better_score(ctx.scorer.objective, &score, best))
+            {
+                best = Some(score);
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn random_hands_match_the_brute_force_optimum() {
+        const POOL: [&str; 16] = [
+            "5S", "6S", "7S", "8S", "9S", "5H", "6H", "7H", "5D", "7D", "6S#2", "7S#2", "5H#2",
+            "7C", "J1", "J2",
+        ];
+        let mut rng = StdRng::seed_from_u64(88);
+        let mut checked = 0;
+        while checked < 60 {
+            let size = rng.gen_range(4..=9);
+            let hand: Vec<&str> = POOL.choose_multiple(&mut rng, size).copied().collect();
+            let hand_mask = parse_hand(&hand).unwrap();
+            let (mask_hi, mask_lo) = combine_mask(hand_mask);
+            let melds = enumerate_melds(mask_hi, mask_lo, &EnumOptions::default());
+            if melds.len() > 16 {
+                continue;
+            }
+            checked += 1;
+            for objective in 0..=OBJ_MAX_CARDS_SAFE {
+                for threshold in [0, 30] {
+                    let options = CoverOptions::default();
+                    let cover = best_cover_with(mask_hi, mask_lo, objective, threshold, &options);
+                    let covered = cover
+                        .deadwood
+                        .iter()
+                        .fold(hand_mask, |mask, &card_id| mask & !card_bitmask(card_id));
+                    search_melds(
+                        &melds,
+                        hand_mask,
+                        objective,
+                        threshold,
+                        &options,
+                        |_, ctx| {
+                            let found = ctx.score(covered, cover.total_points, cover.used_jokers);
+                            let optimum = brute_force_cover(ctx).expect("no cards are required");
+                            assert!(
+                                !better_score(objective, &optimum, &found)
+                                    && !better_score(objective, &found, &optimum),
+                                "{hand:?} objective {objective} threshold {threshold}"
+                            );
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn memoized_search_finds_the_same_cover() {
+        const POOL: [&str; 18] = [
+            "4S", "5S", "6S", "7S", "8S", "9S", "5H", "6H", "7H", "5D", "7D", "6S#2", "7S#2",
+            "5H#2", "7C", "KC", "J1", "J2",
+        ];
+        let mut rng = StdRng::seed_from_u64(35);
+        for _ in 0..60 {
+            let size = rng.gen_range(4..=10);
+            let hand: Vec<&str> = POOL.choose_multiple(&mut rng, size).copied().collect();
+            let hand_mask = parse_hand(&hand).unwrap();
+            let (mask_hi, mask_lo) = combine_mask(hand_mask);
+            let melds = enumerate_melds(mask_hi, mask_lo, &EnumOptions::default());
+            let options = CoverOptions {
+                joker_penalty: [0, 15][rng.gen_range(0..2)],
+                ..Default::default()
+            };
+            for objective in 0..=OBJ_MAX_CARDS_SAFE {
+                for threshold in [0, 30] {
+                    search_melds(
+                        &melds,
+                        hand_mask,
+                        objective,
+                        threshold,
+                        &options,
+                        |_, ctx| {
+                            let cover = |mut memo: Memo| {
+                                let mut best = None;
+                                let mut selection = Vec::new();
+                                search_best_cover(
+                                    ctx,
+                                    0,
+                                    0,
+                                    0,
+                                    0,
+                                    &mut selection,
+                                    &mut best,
+                                    &mut memo,
+                                );
+                                best.map(|(_, selection, points, jokers, _)| {
+                                    (selection, points, jokers)
+                                })
+                            };
+                            assert_eq!(
+                                cover(Memo::new()),
+                                cover(Memo::with_limit(0)),
+                                "{hand:?} objective {objective} threshold {threshold}"
+                            );
+                        },
+                    );
+                }
+            }
+        }
     }
 }
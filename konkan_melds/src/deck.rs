@@ -1,12 +1,123 @@
 //! Card metadata and helpers for the Konkan meld solver.
 
+use crate::bitset::{card_bitmask, mask_cards, merge_words};
+
 pub const NUM_RANKS: usize = 13;
 pub const NUM_SUITS: usize = 4;
+pub const CARDS_PER_DECK: usize = NUM_RANKS * NUM_SUITS;
+/// Highest number of card IDs a `(mask_hi, mask_lo)` pair can address.
+pub const MAX_CARD_IDS: usize = 128;
+/// Joker IDs of `DeckConfig::STANDARD`; other layouts use `DeckConfig::joker_ids`.
 pub const JOKER_IDS: [u8; 2] = [104, 105];
+/// Reserved top mask bit: one abstract joker, for what-if questions such as
+/// "if I had a joker here" without picking a physical joker. `collect_cards`
+/// lists it after the layout's jokers, and a meld that spends it keeps this
+/// bit in its mask. Layouts using all `MAX_CARD_IDS` IDs have no such bit.
+pub const ABSTRACT_JOKER_ID: u8 = (MAX_CARD_IDS - 1) as u8;
+/// Rank `Scoring::deadwood_breakdown` files leftover jokers under, one past
+/// the king.
+pub const JOKER_RANK: u8 = NUM_RANKS as u8;
 pub const KIND_SET: u8 = 0;
 pub const KIND_RUN: u8 = 1;
+/// Two cards short of being a meld, listed only when
+/// `EnumOptions::include_partials` asks for them; never part of a cover.
+pub const KIND_PARTIAL: u8 = 2;
+
+/// Aces only start runs (A-2-3).
+pub const ACE_LOW: u8 = 0;
+/// Aces may start or end runs (A-2-3 and Q-K-A) but runs never wrap.
+pub const ACE_HIGH_LOW: u8 = 1;
+/// Runs may wrap through the ace (K-A-2).
+pub const ACE_WRAP: u8 = 2;
 
 const RANK_POINTS: [i32; NUM_RANKS] = [10, 2, 3, 4, 5, 6, 7, 8, 9, 10, 10, 10, 10];
+/// Penalty charged for a joker left in hand unless the caller picks another.
+pub const DEFAULT_JOKER_PENALTY: i32 = 25;
+/// Largest joker penalty the scoring accepts: charged for every card a hand
+/// mask can hold, it still fits in `i32`.
+pub const MAX_JOKER_PENALTY: i32 = i32::MAX / MAX_CARD_IDS as i32;
+
+/// Shape of the card ID space: `num_decks` standard decks laid out one after
+/// another (ID = copy * 52 + suit * 13 + rank), followed by the jokers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeckConfig {
+    pub num_decks: u8,
+    pub num_jokers: u8,
+    /// Rank (0 = ace) whose cards may also stand in as wild cards, such as
+    /// deuces. Used naturally they keep their own rank and points.
+    pub wild_rank: Option<u8>,
+    /// Fewest cards a set may hold.
+    pub min_set_size: u8,
+    /// Most cards a set may hold. Past `NUM_SUITS` a set repeats a suit,
+    /// taking the second copy from another deck.
+    pub max_set_size: u8,
+}
+
+impl DeckConfig {
+    /// Two standard decks plus two jokers, IDs 0..=105.
+    pub const STANDARD: DeckConfig = DeckConfig {
+        num_decks: 2,
+        num_jokers: 2,
+        wild_rank: None,
+        min_set_size: 3,
+        max_set_size: 4,
+    };
+
+    pub fn first_joker_id(&self) -> u8 {
+        (self.num_decks as usize * CARDS_PER_DECK) as u8
+    }
+
+    pub fn card_count(&self) -> usize {
+        self.num_decks as usize * CARDS_PER_DECK + self.num_jokers as usize
+    }
+
+    pub fn joker_ids(&self) -> Vec<u8> {
+        (self.first_joker_id()..self.card_count() as u8).collect()
+    }
+
+    /// Number of this layout's jokers held in `mask`, the abstract one included.
+    pub fn jokers_in(&self, mask: u128) -> u8 {
+        let abstract_joker = (mask & self.abstract_joker_bit() != 0) as u8;
+        self.joker_ids()
+            .into_iter()
+            .filter(|&card_id| mask & card_bitmask(card_id) != 0)
+            .count() as u8
+            + abstract_joker
+    }
+
+    /// Bits of `mask` at or past `card_count`, which name no card of this
+    /// layout, except the `ABSTRACT_JOKER_ID` bit.
+    pub fn stray_bits(&self, mask: u128) -> u128 {
+        match self.card_count() {
+            count if count >= MAX_CARD_IDS => 0,
+            count => mask >> count << count & !self.abstract_joker_bit(),
+        }
+    }
+
+    /// The `ABSTRACT_JOKER_ID` bit, or 0 when a card of the layout uses it.
+    pub fn abstract_joker_bit(&self) -> u128 {
+        if self.card_count() < MAX_CARD_IDS {
+            card_bitmask(ABSTRACT_JOKER_ID)
+        } else {
+            0
+        }
+    }
+
+    /// Whether every card ID of this layout fits in the 128-bit hand mask.
+    pub fn fits_mask(&self) -> bool {
+        self.num_decks > 0 && self.card_count() <= MAX_CARD_IDS
+    }
+
+    pub fn is_wild(&self, card: &CardInfo) -> bool {
+        self.wild_rank.is_some() && card.rank == self.wild_rank
+    }
+}
+
+impl Default for DeckConfig {
+    fn default() -> Self {
+        DeckConfig::STANDARD
+    }
+}
 
 #[derive(Clone, Copy)]
 pub struct CardInfo {
@@ -15,18 +126,29 @@ pub struct CardInfo {
     pub suit: Option<u8>,
 }
 
-pub fn decode_card(id: u8) -> CardInfo {
-    if id >= JOKER_IDS[0] {
+/// `(rank, suit)` of every ID read as a natural card, so decoding needs no
+/// division. Whether an ID is a joker depends on the layout, and is checked
+/// before the table is read.
+const FACES: [(u8, u8); MAX_CARD_IDS] = {
+    let mut faces = [(0, 0); MAX_CARD_IDS];
+    let mut id = 0;
+    while id < MAX_CARD_IDS {
+        let base = id % CARDS_PER_DECK;
+        faces[id] = ((base % NUM_RANKS) as u8, (base / NUM_RANKS) as u8);
+        id += 1;
+    }
+    faces
+};
+
+pub fn decode_card(id: u8, deck: &DeckConfig) -> CardInfo {
+    if id >= deck.first_joker_id() {
         return CardInfo {
             id,
             rank: None,
             suit: None,
         };
     }
-    let copy = id / 52;
-    let base = id - copy * 52;
-    let suit = base / 13;
-    let rank = base % 13;
+    let (rank, suit) = FACES[id as usize];
     CardInfo {
         id,
         rank: Some(rank),
@@ -34,32 +156,291 @@ pub fn decode_card(id: u8) -> CardInfo {
     }
 }
 
-pub fn points_for_rank(rank: u8) -> i32 {
-    RANK_POINTS[rank as usize]
+/// Point values used to score melds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scoring {
+    /// Points per rank, indexed from the ace (0) to the king (12).
+    pub rank_points: [i32; NUM_RANKS],
+    /// Ace value inside a set, for house rules that score it apart from
+    /// runs; `None` keeps `rank_points[0]`.
+    pub ace_in_set: Option<i32>,
+    /// Ace value at the low end of a run (A-2-3); `None` keeps
+    /// `rank_points[0]`.
+    pub ace_low_in_run: Option<i32>,
+    /// Ace value past the king in a run (Q-K-A, or K-A-2 when runs wrap);
+    /// `None` keeps `rank_points[0]`.
+    pub ace_high_in_run: Option<i32>,
+    /// A printed joker in a meld is worth the card it stands in for, as in
+    /// the standard game; when off it adds nothing to the meld's points.
+    /// Wild cards always score as the card they stand in for.
+    pub joker_scores_as_slot: bool,
+    /// Extra points a meld earns for each printed joker it holds, indexed by
+    /// the joker's place after `DeckConfig::first_joker_id`, for variants
+    /// where one joker (say the red one) is worth more than the other. The
+    /// abstract joker and any jokers past these earn nothing extra.
+    pub joker_bonus: [i32; 2],
+}
+
+impl Scoring {
+    pub const STANDARD: Scoring = Scoring {
+        rank_points: RANK_POINTS,
+        ace_in_set: None,
+        ace_low_in_run: None,
+        ace_high_in_run: None,
+        joker_scores_as_slot: true,
+        joker_bonus: [0; 2],
+    };
+
+    /// Whether every meld, and every cover adding melds up, scores within the
+    /// `i32` range of `Meld::points`: the largest value a card can carry,
+    /// joker bonus included, times every card a hand mask can hold.
+    pub fn fits_points(&self) -> bool {
+        let ace_points = [self.ace_in_set, self.ace_low_in_run, self.ace_high_in_run];
+        let card_points = self
+            .rank_points
+            .iter()
+            .chain(ace_points.iter().flatten())
+            .map(|&points| (points as i64).abs())
+            .max()
+            .unwrap_or(0);
+        let joker_bonus = self
+            .joker_bonus
+            .iter()
+            .map(|&bonus| (bonus as i64).abs())
+            .max()
+            .unwrap_or(0);
+        (card_points + joker_bonus) * MAX_CARD_IDS as i64 <= i32::MAX as i64
+    }
+
+    /// Value of a card of `rank` on its own, as deadwood counts it.
+    pub fn points_for_rank(&self, rank: u8) -> i32 {
+        self.rank_points[rank as usize]
+    }
+
+    /// Value of a card of `rank` melded in a set.
+    pub fn set_points(&self, rank: u8) -> i32 {
+        match self.ace_in_set {
+            Some(points) if rank == 0 => points,
+            _ => self.points_for_rank(rank),
+        }
+    }
+
+    /// Value of a card filling run slot `position`, counted from a low ace
+    /// at 0. Positions past the king repeat the ranks, with a high ace.
+    pub fn run_points(&self, position: usize) -> i32 {
+        let rank = (position % NUM_RANKS) as u8;
+        let ace_points = if position < NUM_RANKS {
+            self.ace_low_in_run
+        } else {
+            self.ace_high_in_run
+        };
+        match ace_points {
+            Some(points) if rank == 0 => points,
+            _ => self.points_for_rank(rank),
+        }
+    }
+
+    /// Value of printed joker `joker_id` of `deck` filling a slot worth
+    /// `slot_points`, its `joker_bonus` included.
+    pub fn joker_points(&self, joker_id: u8, deck: &DeckConfig, slot_points: i32) -> i32 {
+        let slot_points = if self.joker_scores_as_slot {
+            slot_points
+        } else {
+            0
+        };
+        let bonus = joker_id
+            .checked_sub(deck.first_joker_id())
+            .and_then(|joker| self.joker_bonus.get(joker as usize));
+        slot_points.saturating_add(bonus.copied().unwrap_or(0))
+    }
+
+    /// Penalty value of the cards in `mask`: natural cards count their rank
+    /// points and each joker counts `joker_penalty`. The total fits in `i32`
+    /// for a table passing `fits_points` and a penalty in
+    /// `0..=MAX_JOKER_PENALTY`.
+    pub fn deadwood_points(&self, mask: u128, deck: &DeckConfig, joker_penalty: i32) -> i32 {
+        mask_cards(mask)
+            .into_iter()
+            .map(|id| match decode_card(id, deck).rank {
+                Some(rank) => self.points_for_rank(rank),
+                None => joker_penalty,
+            })
+            .sum()
+    }
+
+    /// `deadwood_points` of `mask` split by rank, as `(rank, count, points)`
+    /// from the ace up, skipping ranks with no card. Jokers come last under
+    /// `JOKER_RANK`.
+    pub fn deadwood_breakdown(
+        &self,
+        mask: u128,
+        deck: &DeckConfig,
+        joker_penalty: i32,
+    ) -> Vec<(u8, u8, i32)> {
+        let mut totals = [(0u8, 0i32); NUM_RANKS + 1];
+        for id in mask_cards(mask) {
+            let (rank, points) = match decode_card(id, deck).rank {
+                Some(rank) => (rank, self.points_for_rank(rank)),
+                None => (JOKER_RANK, joker_penalty),
+            };
+            let (count, total) = &mut totals[rank as usize];
+            *count += 1;
+            *total += points;
+        }
+        totals
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, (count, _))| count > 0)
+            .map(|(rank, (count, points))| (rank as u8, count, points))
+            .collect()
+    }
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Scoring::STANDARD
+    }
 }
 
-pub fn collect_cards(mask_hi: u64, mask_lo: u64) -> (Vec<CardInfo>, Vec<u8>) {
+pub fn collect_cards(mask_hi: u64, mask_lo: u64, deck: &DeckConfig) -> (Vec<CardInfo>, Vec<u8>) {
     let mut cards = Vec::new();
     let mut jokers = Vec::new();
-    for id in 0..=105 {
+    let first_joker = deck.first_joker_id() as usize;
+    for id in 0..deck.card_count().min(MAX_CARD_IDS) {
         let bit_present = if id < 64 {
             (mask_lo >> id) & 1 == 1
         } else {
-            let offset = id - 64;
-            if offset >= 64 {
-                false
-            } else {
-                (mask_hi >> offset) & 1 == 1
-            }
+            (mask_hi >> (id - 64)) & 1 == 1
         };
         if !bit_present {
             continue;
         }
-        if id >= JOKER_IDS[0] as usize {
+        if id >= first_joker {
             jokers.push(id as u8);
         } else {
-            cards.push(decode_card(id as u8));
+            cards.push(decode_card(id as u8, deck));
         }
     }
+    if merge_words(mask_hi, mask_lo) & deck.abstract_joker_bit() != 0 {
+        jokers.push(ABSTRACT_JOKER_ID);
+    }
     (cards, jokers)
 }
+
+/// `collect_cards` bucketed by face: the natural card IDs of each rank and
+/// suit as `[rank][suit]`, in ID order so the copies of a card sit side by
+/// side, and the jokers apart.
+pub fn collect_grouped(
+    mask_hi: u64,
+    mask_lo: u64,
+    deck: &DeckConfig,
+) -> (Vec<Vec<Vec<u8>>>, Vec<u8>) {
+    let mut by_rank = vec![vec![Vec::new(); NUM_SUITS]; NUM_RANKS];
+    let mut jokers = Vec::new();
+    collect_grouped_into(mask_hi, mask_lo, deck, &mut by_rank, &mut jokers);
+    (by_rank, jokers)
+}
+
+/// `collect_grouped` into buckets the caller keeps, emptied first.
+pub(crate) fn collect_grouped_into(
+    mask_hi: u64,
+    mask_lo: u64,
+    deck: &DeckConfig,
+    by_rank: &mut [Vec<Vec<u8>>],
+    jokers: &mut Vec<u8>,
+) {
+    for bucket in by_rank.iter_mut().flatten() {
+        bucket.clear();
+    }
+    jokers.clear();
+    let mask = merge_words(mask_hi, mask_lo);
+    let first_joker = deck.first_joker_id();
+    for id in 0..deck.card_count().min(MAX_CARD_IDS) as u8 {
+        if mask & card_bitmask(id) == 0 {
+            continue;
+        }
+        if id >= first_joker {
+            jokers.push(id);
+        } else {
+            let (rank, suit) = FACES[id as usize];
+            by_rank[rank as usize][suit as usize].push(id);
+        }
+    }
+    if mask & deck.abstract_joker_bit() != 0 {
+        jokers.push(ABSTRACT_JOKER_ID);
+    }
+}
+
+/// Hash of the hand as a multiset of faces plus a joker count, so hands that
+/// only differ in which deck copy of a card they hold share a key. The hash
+/// is FNV-1a over the copies held of each face, ace of spades first, then
+/// the jokers, and stays the same across runs and builds.
+pub fn canonical_hand_key(mask_hi: u64, mask_lo: u64, deck: &DeckConfig) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let (cards, jokers) = collect_cards(mask_hi, mask_lo, deck);
+    let mut copies = [0u8; CARDS_PER_DECK];
+    for card in cards {
+        if let (Some(rank), Some(suit)) = (card.rank, card.suit) {
+            copies[suit as usize * NUM_RANKS + rank as usize] += 1;
+        }
+    }
+    copies
+        .into_iter()
+        .chain([jokers.len() as u8])
+        .fold(FNV_OFFSET, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_table_matches_the_arithmetic() {
+        for deck in [
+            DeckConfig::STANDARD,
+            DeckConfig {
+                num_decks: 1,
+                num_jokers: 4,
+                ..DeckConfig::STANDARD
+            },
+        ] {
+            for id in 0..MAX_CARD_IDS as u8 {
+                let card = decode_card(id, &deck);
+                if id >= deck.first_joker_id() {
+                    assert_eq!((card.rank, card.suit), (None, None), "id {id}");
+                    continue;
+                }
+                let base = id % CARDS_PER_DECK as u8;
+                assert_eq!(
+                    (card.rank, card.suit),
+                    (Some(base % 13), Some(base / 13)),
+                    "id {id}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn grouped_cards_match_the_flat_collection() {
+        let deck = DeckConfig::STANDARD;
+        // 7♠ in both copies, 7♥, K♦ and a joker.
+        let mask = [6u8, 58, 19, 51, 104]
+            .iter()
+            .fold(0u128, |mask, &id| mask | card_bitmask(id));
+        let (mask_hi, mask_lo) = ((mask >> 64) as u64, mask as u64);
+        let (by_rank, jokers) = collect_grouped(mask_hi, mask_lo, &deck);
+        let (cards, flat_jokers) = collect_cards(mask_hi, mask_lo, &deck);
+        assert_eq!(jokers, flat_jokers);
+        assert_eq!(by_rank[6][0], [6, 58]);
+        for card in cards {
+            let (rank, suit) = (card.rank.unwrap(), card.suit.unwrap());
+            assert!(by_rank[rank as usize][suit as usize].contains(&card.id));
+        }
+        let grouped: usize = by_rank.iter().flatten().map(Vec::len).sum();
+        assert_eq!(grouped, 4);
+    }
+}
@@ -1,61 +1,257 @@
 //! Rust meld solver for Konkan.
+//!
+//! The Python extension module lives behind the default `python` feature;
+//! without it the crate is a plain Rust library with no pyo3 dependency.
 
+use std::fmt;
+
+#[cfg(feature = "python")]
 use pyo3::prelude::*;
-use pyo3::types::PyModule;
 
+mod binary;
 mod bitset;
 mod cover;
 mod deck;
+mod notation;
+#[cfg(feature = "python")]
+mod python;
 mod runs_sets;
 
-pub use deck::JOKER_IDS;
+pub use binary::{cover_from_bytes, cover_to_bytes, meld_from_bytes, meld_to_bytes, MELD_BYTES};
+pub use bitset::{
+    build_mask, card_bitmask, combine_mask, mask_cards, melds_disjoint_all, melds_overlap,
+    merge_words, union_hands,
+};
+pub use cover::{
+    all_maximal_covers, best_cover_batch, best_cover_core, best_cover_delta, best_cover_dp,
+    best_cover_parallel, best_cover_pure, best_cover_weighted, best_cover_with, best_covers,
+    best_discard, best_single_meld, can_go_out, compare_covers, discard_safety, go_out_layout,
+    min_deadwood, rank_draws, CoreCover, CoverOptions, CoverWeights, ProgressCallback,
+    DEFAULT_TARGET_CARDS,
+};
+pub use deck::{
+    canonical_hand_key, collect_grouped, DeckConfig, Scoring, ABSTRACT_JOKER_ID, ACE_HIGH_LOW,
+    ACE_LOW, ACE_WRAP, DEFAULT_JOKER_PENALTY, JOKER_IDS, JOKER_RANK, KIND_PARTIAL, KIND_RUN,
+    KIND_SET, MAX_JOKER_PENALTY,
+};
+pub use notation::{card_to_string, mask_to_strings, parse_card, parse_hand};
+pub use runs_sets::{
+    classify_meld, count_melds, enumerate_meld_cards, enumerate_melds, enumerate_melds_capped,
+    enumerate_melds_from_ids, enumerate_melds_stats, enumerate_runs, enumerate_sets, joker_swaps,
+    layoff_candidates, melds_after_change, melds_by_card, melds_containing, melds_gained_by_draw,
+    near_runs, validate_meld_with_jokers, EnumOptions, Layoff, MeldIterator, Solver,
+    DEFAULT_MELD_LIMIT,
+};
+
+/// Masks travel as decimal strings because a `u64` can exceed the integer
+/// range JSON consumers such as JavaScript represent exactly.
+#[cfg(feature = "serde")]
+mod mask_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
 
 pub const OBJ_MAX_CARDS: u8 = 0;
 pub const OBJ_MIN_DEADWOOD: u8 = 1;
 pub const OBJ_FIRST_14: u8 = 2;
+pub const OBJ_MAX_POINTS: u8 = 3;
+pub const OBJ_MIN_JOKERS: u8 = 4;
+/// `OBJ_MIN_DEADWOOD`, but among covers leaving as many cards the one whose
+/// leftovers hold more near-melds (pairs and two-card run fragments) wins.
+pub const OBJ_MIN_DEADWOOD_SOFT: u8 = 5;
+/// Highest score under `CoverOptions::weights`; see `best_cover_weighted`.
+pub const OBJ_WEIGHTED: u8 = 6;
+/// `OBJ_MAX_CARDS`, but among covers melding as many cards the one whose
+/// highest-valued leftover card is worth the least wins.
+pub const OBJ_MAX_CARDS_SAFE: u8 = 7;
+
+/// Presentation orders for `enumerate_melds`; see `EnumOptions::order`.
+pub const ORDER_MASK: u8 = 0;
+pub const ORDER_POINTS: u8 = 1;
+pub const ORDER_CARDS: u8 = 2;
 
-#[pyclass]
+#[cfg_attr(feature = "python", pyclass(get_all))]
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Meld {
-    #[pyo3(get)]
+    #[cfg_attr(feature = "serde", serde(with = "mask_string"))]
     pub mask_hi: u64,
-    #[pyo3(get)]
+    #[cfg_attr(feature = "serde", serde(with = "mask_string"))]
     pub mask_lo: u64,
-    #[pyo3(get)]
     pub points: i32,
-    #[pyo3(get)]
     pub jokers_used: u8,
-    #[pyo3(get)]
+    /// Wild-rank cards (such as deuces) standing in for another card.
+    pub wilds_used: u8,
     pub kind: u8,
+    /// No joker or wild card stands in for a card; opening and bonus rules
+    /// often ask for one.
+    pub is_pure: bool,
+    /// `(card_id, points)` for each card in ascending ID order; a joker
+    /// scores as the rank it stands in for.
+    pub points_by_card: Vec<(u8, i32)>,
+    /// Printed jokers the meld consumes, in ascending ID order, so a cover
+    /// can be matched against the physical jokers held.
+    pub joker_ids: Vec<u8>,
+    /// Cards still missing from a `KIND_PARTIAL` meld; 0 for complete melds.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cards_needed: u8,
 }
 
-#[pyclass]
+#[cfg_attr(feature = "python", pyclass(get_all))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CoverResult {
-    #[pyo3(get)]
     pub melds: Vec<Meld>,
-    #[pyo3(get)]
     pub covered_cards: u8,
-    #[pyo3(get)]
     pub total_points: i32,
-    #[pyo3(get)]
     pub used_jokers: u8,
+    pub deadwood: Vec<u8>,
+    /// Number of `deadwood` cards, whatever the hand size.
+    pub deadwood_count: u8,
+    /// The cover's points reach the search threshold.
+    pub meets_threshold: bool,
+    /// The cover melds at least `CoverOptions::target_cards` cards (14 by
+    /// default), the `OBJ_FIRST_14` target.
+    pub target_met: bool,
+    /// The hand holds nothing but jokers, which cannot meld on their own.
+    pub jokers_only: bool,
+    /// The hand holds no cards at all.
+    pub empty_hand: bool,
+    pub timed_out: bool,
+    /// Set when no cover melds every required card.
+    pub infeasible: bool,
+    /// The hand held more melds than `EnumOptions::meld_limit`, so the search
+    /// only chose among the first of them.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub truncated: bool,
+    /// Every meld of the hand the search chose from, kept by
+    /// `best_cover_delta` so the next call can reuse them; empty otherwise.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub candidates: Vec<Meld>,
+}
+
+/// Counters from one `enumerate_melds_stats` call, to judge how costly a
+/// hand will be to cover before searching it.
+#[cfg_attr(feature = "python", pyclass(get_all))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EnumStats {
+    /// Distinct sets returned.
+    pub sets: usize,
+    /// Distinct runs returned.
+    pub runs: usize,
+    /// Candidates dropped because a meld with the same cards was already
+    /// found, such as a run a joker could extend at either end.
+    pub duplicates: usize,
+    /// Cards in the longest run; 0 without runs.
+    pub longest_run: u8,
+    /// `EnumOptions::meld_limit` cut the enumeration short.
+    pub truncated: bool,
+}
+
+impl Meld {
+    /// A set or run of the cards in the mask, checked only for a known kind
+    /// and no more jokers than cards. It carries neither wild cards nor
+    /// per-card points; `joker_ids` and `is_pure` are rebuilt from the mask
+    /// under `DeckConfig::STANDARD`.
+    pub fn new(
+        mask_hi: u64,
+        mask_lo: u64,
+        points: i32,
+        jokers_used: u8,
+        kind: u8,
+    ) -> Result<Meld, String> {
+        if kind != deck::KIND_SET && kind != deck::KIND_RUN {
+            return Err(format!("unknown meld kind {kind}"));
+        }
+        let mask = bitset::merge_words(mask_hi, mask_lo);
+        if jokers_used as u32 > mask.count_ones() {
+            return Err(format!(
+                "a meld of {} cards cannot use {jokers_used} jokers",
+                mask.count_ones()
+            ));
+        }
+        let first_joker = DeckConfig::STANDARD.first_joker_id();
+        Ok(Meld {
+            mask_hi,
+            mask_lo,
+            points,
+            jokers_used,
+            wilds_used: 0,
+            kind,
+            is_pure: jokers_used == 0,
+            points_by_card: Vec::new(),
+            joker_ids: bitset::mask_cards(mask)
+                .into_iter()
+                .filter(|&card_id| card_id >= first_joker)
+                .collect(),
+            cards_needed: 0,
+        })
+    }
+
+    /// Key behind the canonical meld order used by `enumerate_melds` and
+    /// `best_cover`, and behind equality and hashing on the Python side.
+    pub fn sort_key(&self) -> (u64, u64, u8, u8, i32) {
+        (
+            self.mask_hi,
+            self.mask_lo,
+            self.kind,
+            self.jokers_used,
+            self.points,
+        )
+    }
 }
 
-#[pyfunction]
-fn enumerate_melds(mask_hi: u64, mask_lo: u64) -> PyResult<Vec<Meld>> {
-    Ok(runs_sets::enumerate_melds(mask_hi, mask_lo))
+impl fmt::Display for Meld {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            deck::KIND_RUN => "run",
+            deck::KIND_SET => "set",
+            deck::KIND_PARTIAL => "partial",
+            _ => "meld",
+        };
+        let cards = notation::mask_to_strings(bitset::merge_words(self.mask_hi, self.mask_lo))
+            .unwrap_or_default();
+        write!(
+            f,
+            "Meld({kind} [{}], points={}, jokers_used={})",
+            cards.join(" "),
+            self.points,
+            self.jokers_used
+        )
+    }
 }
 
-#[pyfunction]
-fn best_cover(mask_hi: u64, mask_lo: u64, objective: u8, threshold: i32) -> PyResult<CoverResult> {
-    Ok(cover::best_cover(mask_hi, mask_lo, objective, threshold))
+impl fmt::Display for CoverResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "CoverResult(covered_cards={}, total_points={}, used_jokers={}",
+            self.covered_cards, self.total_points, self.used_jokers
+        )?;
+        if self.melds.is_empty() {
+            return write!(f, ")");
+        }
+        for meld in &self.melds {
+            write!(f, "\n    {meld}")?;
+        }
+        write!(f, "\n)")
+    }
 }
 
-#[pymodule]
-fn konkan_melds(_py: Python<'_>, module: &Bound<'_, PyModule>) -> PyResult<()> {
-    module.add_function(wrap_pyfunction!(enumerate_melds, module)?)?;
-    module.add_function(wrap_pyfunction!(best_cover, module)?)?;
-    module.add_class::<Meld>()?;
-    module.add_class::<CoverResult>()?;
-    Ok(())
+/// Best cover of the hand under `objective` with the default search options.
+pub fn best_cover(mask_hi: u64, mask_lo: u64, objective: u8, threshold: i32) -> CoverResult {
+    best_cover_with(
+        mask_hi,
+        mask_lo,
+        objective,
+        threshold,
+        &CoverOptions::default(),
+    )
 }
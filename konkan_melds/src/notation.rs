@@ -0,0 +1,93 @@
+//! Human-readable card notation: "TH" (ten of hearts), "2S#2" (second-deck
+//! two of spades), "J1"/"J2" for the jokers and "J*" for the abstract joker.
+
+use crate::bitset::{card_bitmask, mask_cards};
+use crate::deck::{
+    decode_card, DeckConfig, ABSTRACT_JOKER_ID, CARDS_PER_DECK, JOKER_IDS, NUM_RANKS,
+};
+
+pub(crate) const RANK_TOKENS: [char; NUM_RANKS] = [
+    'A', '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K',
+];
+const SUIT_TOKENS: [char; 4] = ['S', 'H', 'D', 'C'];
+
+pub fn parse_card(token: &str) -> Result<u8, String> {
+    let upper = token.trim().to_ascii_uppercase();
+    let (body, copy) = match upper.split_once('#') {
+        Some((body, "1")) => (body, 0),
+        Some((body, "2")) => (body, 1),
+        Some(_) => {
+            return Err(format!(
+                "invalid deck copy in card {token:?}; expected #1 or #2"
+            ))
+        }
+        None => (upper.as_str(), 0),
+    };
+
+    match body {
+        "J1" if copy == 0 => return Ok(JOKER_IDS[0]),
+        "J2" if copy == 0 => return Ok(JOKER_IDS[1]),
+        "J*" if copy == 0 => return Ok(ABSTRACT_JOKER_ID),
+        "J1" | "J2" | "J*" => return Err(format!("jokers take no deck copy: {token:?}")),
+        _ => {}
+    }
+
+    // "10" is accepted as an alias for "T".
+    let body = body
+        .strip_prefix("10")
+        .map_or(body.to_string(), |suit| format!("T{suit}"));
+    let mut chars = body.chars();
+    let (Some(rank_char), Some(suit_char), None) = (chars.next(), chars.next(), chars.next())
+    else {
+        return Err(format!(
+            "malformed card {token:?}; expected rank+suit such as \"TH\""
+        ));
+    };
+    let rank = RANK_TOKENS
+        .iter()
+        .position(|&c| c == rank_char)
+        .ok_or_else(|| format!("unknown rank {rank_char:?} in card {token:?}"))?;
+    let suit = SUIT_TOKENS
+        .iter()
+        .position(|&c| c == suit_char)
+        .ok_or_else(|| format!("unknown suit {suit_char:?} in card {token:?}"))?;
+    Ok((copy * CARDS_PER_DECK + suit * NUM_RANKS + rank) as u8)
+}
+
+pub fn parse_hand<S: AsRef<str>>(tokens: &[S]) -> Result<u128, String> {
+    let mut mask: u128 = 0;
+    for token in tokens {
+        let bit = card_bitmask(parse_card(token.as_ref())?);
+        if mask & bit != 0 {
+            return Err(format!("card {:?} listed twice", token.as_ref()));
+        }
+        mask |= bit;
+    }
+    Ok(mask)
+}
+
+pub fn card_to_string(id: u8) -> Result<String, String> {
+    let deck = DeckConfig::STANDARD;
+    if id == ABSTRACT_JOKER_ID {
+        return Ok("J*".to_string());
+    }
+    if id as usize >= deck.card_count() {
+        return Err(format!("card id {id} is outside 0..{}", deck.card_count()));
+    }
+    let info = decode_card(id, &deck);
+    let (Some(rank), Some(suit)) = (info.rank, info.suit) else {
+        return Ok(format!("J{}", id - deck.first_joker_id() + 1));
+    };
+    let mut token = format!(
+        "{}{}",
+        RANK_TOKENS[rank as usize], SUIT_TOKENS[suit as usize]
+    );
+    if id as usize >= CARDS_PER_DECK {
+        token.push_str("#2");
+    }
+    Ok(token)
+}
+
+pub fn mask_to_strings(mask: u128) -> Result<Vec<String>, String> {
+    mask_cards(mask).into_iter().map(card_to_string).collect()
+}
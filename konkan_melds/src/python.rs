@@ -0,0 +1,1288 @@
+//! Python bindings, built with the `python` feature.
+
+// pyo3 0.22's `#[pyfunction]` expansion converts `PyErr` into itself for every
+// binding returning `PyResult`, which clippy flags at each signature.
+#![allow(clippy::useless_conversion)]
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::pyclass::CompareOp;
+use pyo3::types::{PyBytes, PyModule};
+
+use crate::deck::{self, DeckConfig, Scoring, ACE_LOW, KIND_PARTIAL, KIND_RUN, KIND_SET};
+use crate::runs_sets::{self, MeldIterator, Solver};
+use crate::{
+    binary, bitset, cover, notation, CoverResult, EnumStats, Meld, OBJ_FIRST_14, OBJ_MAX_CARDS,
+    OBJ_MAX_CARDS_SAFE, OBJ_MAX_POINTS, OBJ_MIN_DEADWOOD, OBJ_MIN_DEADWOOD_SOFT, OBJ_MIN_JOKERS,
+    OBJ_WEIGHTED, ORDER_MASK,
+};
+
+#[pymethods]
+impl Meld {
+    #[new]
+    fn py_new(
+        mask_hi: u64,
+        mask_lo: u64,
+        points: i32,
+        jokers_used: u8,
+        kind: u8,
+    ) -> PyResult<Self> {
+        check_mask(mask_hi, mask_lo, &DeckConfig::STANDARD)?;
+        Meld::new(mask_hi, mask_lo, points, jokers_used, kind).map_err(PyValueError::new_err)
+    }
+
+    fn card_points(&self) -> Vec<(u8, i32)> {
+        self.points_by_card.clone()
+    }
+
+    fn __richcmp__(&self, other: &Self, op: CompareOp) -> bool {
+        op.matches(self.sort_key().cmp(&other.sort_key()))
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.sort_key().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[cfg(feature = "serde")]
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(self).map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    #[cfg(feature = "serde")]
+    #[staticmethod]
+    fn from_json(data: &str) -> PyResult<Self> {
+        serde_json::from_str(data).map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        self.to_string()
+    }
+
+    fn __str__(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[pymethods]
+impl MeldIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<'_, Self>) -> Option<Meld> {
+        slf.next()
+    }
+}
+
+#[pymethods]
+impl Solver {
+    #[new]
+    #[pyo3(signature = (
+        ace_mode = ACE_LOW,
+        num_decks = 2,
+        num_jokers = 2,
+        rank_points = None,
+        wild_rank = None,
+        joker_bonus = None,
+    ))]
+    fn py_new(
+        ace_mode: u8,
+        num_decks: u8,
+        num_jokers: u8,
+        rank_points: Option<[i32; 13]>,
+        wild_rank: Option<u8>,
+        joker_bonus: Option<[i32; 2]>,
+    ) -> PyResult<Self> {
+        let options = enum_options(
+            ace_mode,
+            num_decks,
+            num_jokers,
+            wild_rank,
+            rank_points,
+            joker_bonus,
+        )?;
+        Ok(Solver::new(&options))
+    }
+
+    #[pyo3(name = "enumerate")]
+    fn py_enumerate(&mut self, mask_hi: u64, mask_lo: u64) -> PyResult<Vec<Meld>> {
+        check_mask(mask_hi, mask_lo, &self.options().deck)?;
+        Ok(self.enumerate(mask_hi, mask_lo))
+    }
+}
+
+#[pymethods]
+impl CoverResult {
+    fn count_pure_melds(&self) -> usize {
+        self.melds.iter().filter(|meld| meld.is_pure).count()
+    }
+
+    fn coverage_mask(&self) -> (u64, u64) {
+        let mask = self.melds.iter().fold(0u128, |mask, meld| {
+            mask | bitset::merge_words(meld.mask_hi, meld.mask_lo)
+        });
+        bitset::combine_mask(mask)
+    }
+
+    #[cfg(feature = "serde")]
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(self).map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    #[cfg(feature = "serde")]
+    #[staticmethod]
+    fn from_json(data: &str) -> PyResult<Self> {
+        serde_json::from_str(data).map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        self.to_string()
+    }
+
+    fn __str__(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[pyfunction]
+#[pyo3(signature = (
+    hand_hi,
+    hand_lo,
+    covered_hi,
+    covered_lo,
+    joker_penalty = deck::DEFAULT_JOKER_PENALTY,
+    rank_points = None,
+))]
+fn deadwood_points(
+    hand_hi: u64,
+    hand_lo: u64,
+    covered_hi: u64,
+    covered_lo: u64,
+    joker_penalty: i32,
+    rank_points: Option<[i32; 13]>,
+) -> PyResult<i32> {
+    let deck = DeckConfig::STANDARD;
+    check_mask(hand_hi, hand_lo, &deck)?;
+    check_mask(covered_hi, covered_lo, &deck)?;
+    check_joker_penalty(joker_penalty)?;
+    let leftover =
+        bitset::merge_words(hand_hi, hand_lo) & !bitset::merge_words(covered_hi, covered_lo);
+    Ok(scoring_from(rank_points, None)?.deadwood_points(leftover, &deck, joker_penalty))
+}
+
+#[pyfunction]
+#[pyo3(signature = (
+    hand_hi,
+    hand_lo,
+    covered_hi,
+    covered_lo,
+    joker_penalty = deck::DEFAULT_JOKER_PENALTY,
+    rank_points = None,
+))]
+fn deadwood_breakdown(
+    hand_hi: u64,
+    hand_lo: u64,
+    covered_hi: u64,
+    covered_lo: u64,
+    joker_penalty: i32,
+    rank_points: Option<[i32; 13]>,
+) -> PyResult<Vec<(u8, u8, i32)>> {
+    let deck = DeckConfig::STANDARD;
+    check_mask(hand_hi, hand_lo, &deck)?;
+    check_mask(covered_hi, covered_lo, &deck)?;
+    check_joker_penalty(joker_penalty)?;
+    let leftover =
+        bitset::merge_words(hand_hi, hand_lo) & !bitset::merge_words(covered_hi, covered_lo);
+    Ok(scoring_from(rank_points, None)?.deadwood_breakdown(leftover, &deck, joker_penalty))
+}
+
+#[pyfunction]
+fn parse_card(card: &str) -> PyResult<u8> {
+    notation::parse_card(card).map_err(PyValueError::new_err)
+}
+
+#[pyfunction]
+fn parse_hand(cards: Vec<String>) -> PyResult<(u64, u64)> {
+    let mask = notation::parse_hand(&cards).map_err(PyValueError::new_err)?;
+    Ok(bitset::combine_mask(mask))
+}
+
+#[pyfunction]
+fn card_to_string(card_id: u8) -> PyResult<String> {
+    notation::card_to_string(card_id).map_err(PyValueError::new_err)
+}
+
+#[pyfunction]
+fn mask_to_strings(mask_hi: u64, mask_lo: u64) -> PyResult<Vec<String>> {
+    notation::mask_to_strings(bitset::merge_words(mask_hi, mask_lo)).map_err(PyValueError::new_err)
+}
+
+#[pyfunction]
+#[pyo3(signature = (card_ids, strict = false))]
+fn build_mask(card_ids: Vec<u8>, strict: bool) -> PyResult<(u64, u64)> {
+    let mask = bitset::build_mask(&card_ids, DeckConfig::STANDARD.card_count(), strict)
+        .map_err(PyValueError::new_err)?;
+    Ok(bitset::combine_mask(mask))
+}
+
+#[pyfunction]
+fn mask_to_cards(mask_hi: u64, mask_lo: u64) -> Vec<u8> {
+    bitset::mask_cards(bitset::merge_words(mask_hi, mask_lo))
+}
+
+#[pyfunction]
+#[pyo3(signature = (mask_hi, mask_lo, num_decks = 2, num_jokers = 2))]
+fn canonical_hand_key(mask_hi: u64, mask_lo: u64, num_decks: u8, num_jokers: u8) -> PyResult<u64> {
+    let options = enum_options(ACE_LOW, num_decks, num_jokers, None, None, None)?;
+    check_mask(mask_hi, mask_lo, &options.deck)?;
+    Ok(deck::canonical_hand_key(mask_hi, mask_lo, &options.deck))
+}
+
+#[pyfunction]
+fn melds_overlap(a_hi: u64, a_lo: u64, b_hi: u64, b_lo: u64) -> bool {
+    bitset::melds_overlap(a_hi, a_lo, b_hi, b_lo)
+}
+
+#[pyfunction]
+fn melds_disjoint_all(masks: Vec<(u64, u64)>) -> bool {
+    bitset::melds_disjoint_all(&masks)
+}
+
+#[pyfunction]
+fn meld_to_bytes<'py>(py: Python<'py>, meld: PyRef<'_, Meld>) -> Bound<'py, PyBytes> {
+    PyBytes::new_bound(py, &binary::meld_to_bytes(&meld))
+}
+
+#[pyfunction]
+fn meld_from_bytes(data: &[u8]) -> PyResult<Meld> {
+    binary::meld_from_bytes(data).map_err(PyValueError::new_err)
+}
+
+#[pyfunction]
+fn cover_to_bytes<'py>(py: Python<'py>, cover: PyRef<'_, CoverResult>) -> Bound<'py, PyBytes> {
+    PyBytes::new_bound(py, &binary::cover_to_bytes(&cover))
+}
+
+#[pyfunction]
+fn cover_from_bytes(data: &[u8]) -> PyResult<CoverResult> {
+    binary::cover_from_bytes(data).map_err(PyValueError::new_err)
+}
+
+#[pyfunction]
+fn union_hands(a_hi: u64, a_lo: u64, b_hi: u64, b_lo: u64) -> (u64, u64, Vec<u8>) {
+    bitset::union_hands(a_hi, a_lo, b_hi, b_lo)
+}
+
+/// Rejects a hand mask with bits past the layout's card IDs, which usually
+/// means it was built for another layout.
+fn check_mask(mask_hi: u64, mask_lo: u64, deck: &DeckConfig) -> PyResult<()> {
+    let stray = deck.stray_bits(bitset::merge_words(mask_hi, mask_lo));
+    if stray != 0 {
+        return Err(PyValueError::new_err(format!(
+            "mask sets bit {} outside card ids 0..{}",
+            stray.trailing_zeros(),
+            deck.card_count()
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects a joker penalty outside `0..=MAX_JOKER_PENALTY`, where charging
+/// it could overflow or turn it into a bonus.
+fn check_joker_penalty(joker_penalty: i32) -> PyResult<()> {
+    if !(0..=deck::MAX_JOKER_PENALTY).contains(&joker_penalty) {
+        return Err(PyValueError::new_err(format!(
+            "joker_penalty {joker_penalty} is outside 0..={}",
+            deck::MAX_JOKER_PENALTY
+        )));
+    }
+    Ok(())
+}
+
+fn scoring_from(
+    rank_points: Option<[i32; 13]>,
+    joker_bonus: Option<[i32; 2]>,
+) -> PyResult<Scoring> {
+    let scoring = Scoring {
+        rank_points: rank_points.unwrap_or(Scoring::STANDARD.rank_points),
+        joker_bonus: joker_bonus.unwrap_or(Scoring::STANDARD.joker_bonus),
+        ..Scoring::STANDARD
+    };
+    if !scoring.fits_points() {
+        return Err(PyValueError::new_err(format!(
+            "rank points {:?} and joker bonus {:?} can add up past the i32 range of meld points",
+            scoring.rank_points, scoring.joker_bonus
+        )));
+    }
+    Ok(scoring)
+}
+
+fn enum_options(
+    ace_mode: u8,
+    num_decks: u8,
+    num_jokers: u8,
+    wild_rank: Option<u8>,
+    rank_points: Option<[i32; 13]>,
+    joker_bonus: Option<[i32; 2]>,
+) -> PyResult<runs_sets::EnumOptions> {
+    let deck = DeckConfig {
+        num_decks,
+        num_jokers,
+        wild_rank,
+        ..DeckConfig::STANDARD
+    };
+    if !deck.fits_mask() {
+        return Err(PyValueError::new_err(format!(
+            "{num_decks} decks and {num_jokers} jokers do not fit in a 128-bit hand mask"
+        )));
+    }
+    if let Some(rank) = wild_rank.filter(|&rank| rank as usize >= deck::NUM_RANKS) {
+        return Err(PyValueError::new_err(format!(
+            "wild rank {rank} is outside 0..{}",
+            deck::NUM_RANKS
+        )));
+    }
+    Ok(runs_sets::EnumOptions {
+        ace_mode,
+        deck,
+        scoring: scoring_from(rank_points, joker_bonus)?,
+        ..Default::default()
+    })
+}
+
+#[pyfunction]
+#[pyo3(signature = (
+    mask_hi,
+    mask_lo,
+    ace_mode = ACE_LOW,
+    num_decks = 2,
+    num_jokers = 2,
+    rank_points = None,
+    wild_rank = None,
+    only_maximal_runs = false,
+    max_run_len = 0,
+    order = ORDER_MASK,
+    min_set_size = 3,
+    max_set_size = 4,
+    meld_limit = runs_sets::DEFAULT_MELD_LIMIT,
+    max_jokers_per_meld = u8::MAX,
+    include_partials = false,
+    joker_bonus = None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn enumerate_melds(
+    mask_hi: u64,
+    mask_lo: u64,
+    ace_mode: u8,
+    num_decks: u8,
+    num_jokers: u8,
+    rank_points: Option<[i32; 13]>,
+    wild_rank: Option<u8>,
+    only_maximal_runs: bool,
+    max_run_len: usize,
+    order: u8,
+    min_set_size: u8,
+    max_set_size: u8,
+    meld_limit: usize,
+    max_jokers_per_meld: u8,
+    include_partials: bool,
+    joker_bonus: Option<[i32; 2]>,
+) -> PyResult<Vec<Meld>> {
+    let mut options = enum_options(
+        ace_mode,
+        num_decks,
+        num_jokers,
+        wild_rank,
+        rank_points,
+        joker_bonus,
+    )?;
+    set_sizes(&mut options, min_set_size, max_set_size)?;
+    check_mask(mask_hi, mask_lo, &options.deck)?;
+    options.only_maximal_runs = only_maximal_runs;
+    options.max_run_len = max_run_len;
+    options.order = order;
+    options.meld_limit = meld_limit;
+    options.max_jokers_per_meld = max_jokers_per_meld;
+    options.include_partials = include_partials;
+    Ok(runs_sets::enumerate_melds(mask_hi, mask_lo, &options))
+}
+
+#[pyfunction]
+#[pyo3(signature = (
+    mask_hi,
+    mask_lo,
+    ace_mode = ACE_LOW,
+    num_decks = 2,
+    num_jokers = 2,
+    rank_points = None,
+    wild_rank = None,
+    only_maximal_runs = false,
+    max_run_len = 0,
+    order = ORDER_MASK,
+    collapse_copies = false,
+    max_jokers_per_meld = u8::MAX,
+    joker_bonus = None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn enumerate_runs(
+    mask_hi: u64,
+    mask_lo: u64,
+    ace_mode: u8,
+    num_decks: u8,
+    num_jokers: u8,
+    rank_points: Option<[i32; 13]>,
+    wild_rank: Option<u8>,
+    only_maximal_runs: bool,
+    max_run_len: usize,
+    order: u8,
+    collapse_copies: bool,
+    max_jokers_per_meld: u8,
+    joker_bonus: Option<[i32; 2]>,
+) -> PyResult<Vec<Meld>> {
+    let mut options = enum_options(
+        ace_mode,
+        num_decks,
+        num_jokers,
+        wild_rank,
+        rank_points,
+        joker_bonus,
+    )?;
+    check_mask(mask_hi, mask_lo, &options.deck)?;
+    options.only_maximal_runs = only_maximal_runs;
+    options.max_run_len = max_run_len;
+    options.order = order;
+    options.collapse_copies = collapse_copies;
+    options.max_jokers_per_meld = max_jokers_per_meld;
+    Ok(runs_sets::enumerate_runs(mask_hi, mask_lo, &options))
+}
+
+#[pyfunction]
+#[pyo3(signature = (
+    mask_hi,
+    mask_lo,
+    num_decks = 2,
+    num_jokers = 2,
+    rank_points = None,
+    wild_rank = None,
+    order = ORDER_MASK,
+    min_set_size = 3,
+    max_set_size = 4,
+    dedup_joker_assignments = false,
+    max_jokers_per_meld = u8::MAX,
+    joker_bonus = None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn enumerate_sets(
+    mask_hi: u64,
+    mask_lo: u64,
+    num_decks: u8,
+    num_jokers: u8,
+    rank_points: Option<[i32; 13]>,
+    wild_rank: Option<u8>,
+    order: u8,
+    min_set_size: u8,
+    max_set_size: u8,
+    dedup_joker_assignments: bool,
+    max_jokers_per_meld: u8,
+    joker_bonus: Option<[i32; 2]>,
+) -> PyResult<Vec<Meld>> {
+    let mut options = enum_options(
+        ACE_LOW,
+        num_decks,
+        num_jokers,
+        wild_rank,
+        rank_points,
+        joker_bonus,
+    )?;
+    set_sizes(&mut options, min_set_size, max_set_size)?;
+    check_mask(mask_hi, mask_lo, &options.deck)?;
+    options.order = order;
+    options.dedup_joker_assignments = dedup_joker_assignments;
+    options.max_jokers_per_meld = max_jokers_per_meld;
+    Ok(runs_sets::enumerate_sets(mask_hi, mask_lo, &options))
+}
+
+#[pyfunction]
+#[pyo3(signature = (
+    mask_hi,
+    mask_lo,
+    ace_mode = ACE_LOW,
+    num_decks = 2,
+    num_jokers = 2,
+    rank_points = None,
+    wild_rank = None,
+    order = ORDER_MASK,
+    meld_limit = runs_sets::DEFAULT_MELD_LIMIT,
+    joker_bonus = None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn enumerate_melds_stats(
+    mask_hi: u64,
+    mask_lo: u64,
+    ace_mode: u8,
+    num_decks: u8,
+    num_jokers: u8,
+    rank_points: Option<[i32; 13]>,
+    wild_rank: Option<u8>,
+    order: u8,
+    meld_limit: usize,
+    joker_bonus: Option<[i32; 2]>,
+) -> PyResult<(Vec<Meld>, EnumStats)> {
+    let mut options = enum_options(
+        ace_mode,
+        num_decks,
+        num_jokers,
+        wild_rank,
+        rank_points,
+        joker_bonus,
+    )?;
+    check_mask(mask_hi, mask_lo, &options.deck)?;
+    options.order = order;
+    options.meld_limit = meld_limit;
+    Ok(runs_sets::enumerate_melds_stats(mask_hi, mask_lo, &options))
+}
+
+fn set_sizes(
+    options: &mut runs_sets::EnumOptions,
+    min_set_size: u8,
+    max_set_size: u8,
+) -> PyResult<()> {
+    if min_set_size < 2 {
+        return Err(PyValueError::new_err(format!(
+            "min_set_size {min_set_size} is below 2"
+        )));
+    }
+    if min_set_size > max_set_size {
+        return Err(PyValueError::new_err(format!(
+            "min_set_size {min_set_size} exceeds max_set_size {max_set_size}"
+        )));
+    }
+    options.deck.min_set_size = min_set_size;
+    options.deck.max_set_size = max_set_size;
+    Ok(())
+}
+
+#[pyfunction]
+#[pyo3(signature = (card_ids, ace_mode = ACE_LOW, num_decks = 2, num_jokers = 2))]
+fn enumerate_melds_from_ids(
+    card_ids: Vec<u8>,
+    ace_mode: u8,
+    num_decks: u8,
+    num_jokers: u8,
+) -> PyResult<Vec<Meld>> {
+    let options = enum_options(ace_mode, num_decks, num_jokers, None, None, None)?;
+    runs_sets::enumerate_melds_from_ids(&card_ids, &options).map_err(PyValueError::new_err)
+}
+
+#[pyfunction]
+#[pyo3(signature = (mask_hi, mask_lo, ace_mode = ACE_LOW, num_decks = 2, num_jokers = 2))]
+fn count_melds(
+    mask_hi: u64,
+    mask_lo: u64,
+    ace_mode: u8,
+    num_decks: u8,
+    num_jokers: u8,
+) -> PyResult<usize> {
+    let options = enum_options(ace_mode, num_decks, num_jokers, None, None, None)?;
+    check_mask(mask_hi, mask_lo, &options.deck)?;
+    Ok(runs_sets::count_melds(mask_hi, mask_lo, &options))
+}
+
+#[pyfunction]
+#[pyo3(signature = (
+    mask_hi,
+    mask_lo,
+    ace_mode = ACE_LOW,
+    num_decks = 2,
+    num_jokers = 2,
+    rank_points = None,
+    wild_rank = None,
+    joker_bonus = None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn melds_by_card(
+    mask_hi: u64,
+    mask_lo: u64,
+    ace_mode: u8,
+    num_decks: u8,
+    num_jokers: u8,
+    rank_points: Option<[i32; 13]>,
+    wild_rank: Option<u8>,
+    joker_bonus: Option<[i32; 2]>,
+) -> PyResult<Vec<(u8, Vec<usize>)>> {
+    let options = enum_options(
+        ace_mode,
+        num_decks,
+        num_jokers,
+        wild_rank,
+        rank_points,
+        joker_bonus,
+    )?;
+    check_mask(mask_hi, mask_lo, &options.deck)?;
+    Ok(runs_sets::melds_by_card(mask_hi, mask_lo, &options))
+}
+
+#[pyfunction]
+#[pyo3(signature = (mask_hi, mask_lo, card_id, ace_mode = ACE_LOW))]
+fn melds_containing(mask_hi: u64, mask_lo: u64, card_id: u8, ace_mode: u8) -> PyResult<Vec<Meld>> {
+    let options = runs_sets::EnumOptions {
+        ace_mode,
+        ..Default::default()
+    };
+    check_mask(mask_hi, mask_lo, &options.deck)?;
+    let card_count = options.deck.card_count();
+    if card_id as usize >= card_count {
+        return Err(PyValueError::new_err(format!(
+            "card id {card_id} is outside 0..{card_count}"
+        )));
+    }
+    Ok(runs_sets::melds_containing(
+        mask_hi, mask_lo, card_id, &options,
+    ))
+}
+
+#[pyfunction]
+#[pyo3(signature = (card_ids, kind, ace_mode = ACE_LOW))]
+fn validate_meld_with_jokers(card_ids: Vec<u8>, kind: u8, ace_mode: u8) -> PyResult<bool> {
+    let options = runs_sets::EnumOptions {
+        ace_mode,
+        ..Default::default()
+    };
+    runs_sets::validate_meld_with_jokers(&card_ids, kind, &options)
+        .map_err(PyValueError::new_err)?;
+    Ok(true)
+}
+
+#[pyfunction]
+#[pyo3(signature = (mask_hi, mask_lo, ace_mode = ACE_LOW))]
+fn classify_meld(mask_hi: u64, mask_lo: u64, ace_mode: u8) -> PyResult<Option<u8>> {
+    let options = runs_sets::EnumOptions {
+        ace_mode,
+        ..Default::default()
+    };
+    check_mask(mask_hi, mask_lo, &options.deck)?;
+    Ok(runs_sets::classify_meld(mask_hi, mask_lo, &options))
+}
+
+#[pyfunction]
+#[pyo3(signature = (hand_hi, hand_lo, meld_hi, meld_lo, ace_mode = ACE_LOW))]
+fn joker_swaps(
+    hand_hi: u64,
+    hand_lo: u64,
+    meld_hi: u64,
+    meld_lo: u64,
+    ace_mode: u8,
+) -> PyResult<Vec<(u8, u8)>> {
+    let options = runs_sets::EnumOptions {
+        ace_mode,
+        ..Default::default()
+    };
+    check_mask(hand_hi, hand_lo, &options.deck)?;
+    check_mask(meld_hi, meld_lo, &options.deck)?;
+    Ok(runs_sets::joker_swaps(
+        hand_hi, hand_lo, meld_hi, meld_lo, &options,
+    ))
+}
+
+#[pyfunction]
+#[pyo3(signature = (hand_hi, hand_lo, table_melds, swap_jokers = false, ace_mode = ACE_LOW))]
+fn layoff_candidates(
+    hand_hi: u64,
+    hand_lo: u64,
+    table_melds: Vec<(u64, u64)>,
+    swap_jokers: bool,
+    ace_mode: u8,
+) -> PyResult<Vec<runs_sets::Layoff>> {
+    let options = runs_sets::EnumOptions {
+        ace_mode,
+        ..Default::default()
+    };
+    check_mask(hand_hi, hand_lo, &options.deck)?;
+    for &(meld_hi, meld_lo) in &table_melds {
+        check_mask(meld_hi, meld_lo, &options.deck)?;
+    }
+    Ok(runs_sets::layoff_candidates(
+        hand_hi,
+        hand_lo,
+        &table_melds,
+        swap_jokers,
+        &options,
+    ))
+}
+
+#[pyfunction]
+#[pyo3(signature = (mask_hi, mask_lo, max_missing = 1, ace_mode = ACE_LOW))]
+fn near_runs(
+    mask_hi: u64,
+    mask_lo: u64,
+    max_missing: u8,
+    ace_mode: u8,
+) -> PyResult<Vec<(Vec<u8>, Vec<u8>)>> {
+    let options = runs_sets::EnumOptions {
+        ace_mode,
+        ..Default::default()
+    };
+    check_mask(mask_hi, mask_lo, &options.deck)?;
+    Ok(runs_sets::near_runs(
+        mask_hi,
+        mask_lo,
+        max_missing,
+        &options,
+    ))
+}
+
+#[pyfunction]
+#[pyo3(signature = (mask_hi, mask_lo, candidate_ids, ace_mode = ACE_LOW))]
+fn melds_gained_by_draw(
+    mask_hi: u64,
+    mask_lo: u64,
+    candidate_ids: Vec<u8>,
+    ace_mode: u8,
+) -> PyResult<Vec<(u8, Vec<Meld>)>> {
+    let options = runs_sets::EnumOptions {
+        ace_mode,
+        ..Default::default()
+    };
+    check_mask(mask_hi, mask_lo, &options.deck)?;
+    let card_count = options.deck.card_count();
+    if let Some(card_id) = candidate_ids.iter().find(|&&id| id as usize >= card_count) {
+        return Err(PyValueError::new_err(format!(
+            "card id {card_id} is outside 0..{card_count}"
+        )));
+    }
+    Ok(runs_sets::melds_gained_by_draw(
+        mask_hi,
+        mask_lo,
+        &candidate_ids,
+        &options,
+    ))
+}
+
+#[pyfunction]
+#[pyo3(signature = (
+    mask_hi,
+    mask_lo,
+    ace_mode = ACE_LOW,
+    num_decks = 2,
+    num_jokers = 2,
+    rank_points = None,
+    wild_rank = None,
+    joker_bonus = None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn iter_melds(
+    mask_hi: u64,
+    mask_lo: u64,
+    ace_mode: u8,
+    num_decks: u8,
+    num_jokers: u8,
+    rank_points: Option<[i32; 13]>,
+    wild_rank: Option<u8>,
+    joker_bonus: Option<[i32; 2]>,
+) -> PyResult<MeldIterator> {
+    let options = enum_options(
+        ace_mode,
+        num_decks,
+        num_jokers,
+        wild_rank,
+        rank_points,
+        joker_bonus,
+    )?;
+    check_mask(mask_hi, mask_lo, &options.deck)?;
+    Ok(MeldIterator::new(mask_hi, mask_lo, &options))
+}
+
+#[pyfunction]
+#[pyo3(signature = (
+    mask_hi,
+    mask_lo,
+    objective,
+    threshold,
+    rank_points = None,
+    min_first_points = 0,
+    forbidden_hi = 0,
+    forbidden_lo = 0,
+    max_melds = 0,
+    num_decks = 2,
+    num_jokers = 2,
+    joker_penalty = 0,
+    target_cards = cover::DEFAULT_TARGET_CARDS,
+    meld_limit = runs_sets::DEFAULT_MELD_LIMIT,
+    max_jokers_per_meld = u8::MAX,
+    joker_bonus = None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn best_cover(
+    mask_hi: u64,
+    mask_lo: u64,
+    objective: u8,
+    threshold: i32,
+    rank_points: Option<[i32; 13]>,
+    min_first_points: i32,
+    forbidden_hi: u64,
+    forbidden_lo: u64,
+    max_melds: usize,
+    num_decks: u8,
+    num_jokers: u8,
+    joker_penalty: i32,
+    target_cards: u8,
+    meld_limit: usize,
+    max_jokers_per_meld: u8,
+    joker_bonus: Option<[i32; 2]>,
+) -> PyResult<CoverResult> {
+    let options = cover::CoverOptions {
+        enumeration: runs_sets::EnumOptions {
+            meld_limit,
+            max_jokers_per_meld,
+            ..enum_options(
+                ACE_LOW,
+                num_decks,
+                num_jokers,
+                None,
+                rank_points,
+                joker_bonus,
+            )?
+        },
+        min_first_points,
+        forbidden_mask: bitset::merge_words(forbidden_hi, forbidden_lo),
+        max_melds,
+        joker_penalty,
+        target_cards,
+        ..Default::default()
+    };
+    check_joker_penalty(joker_penalty)?;
+    check_mask(mask_hi, mask_lo, &options.enumeration.deck)?;
+    Ok(cover::best_cover_with(
+        mask_hi, mask_lo, objective, threshold, &options,
+    ))
+}
+
+#[pyfunction]
+#[pyo3(signature = (
+    mask_hi,
+    mask_lo,
+    objective,
+    threshold,
+    required_hi,
+    required_lo,
+    rank_points = None,
+    min_first_points = 0,
+    forbidden_hi = 0,
+    forbidden_lo = 0,
+    max_melds = 0,
+    num_decks = 2,
+    num_jokers = 2,
+    joker_penalty = 0,
+    target_cards = cover::DEFAULT_TARGET_CARDS,
+    meld_limit = runs_sets::DEFAULT_MELD_LIMIT,
+    max_jokers_per_meld = u8::MAX,
+    joker_bonus = None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn best_cover_constrained(
+    mask_hi: u64,
+    mask_lo: u64,
+    objective: u8,
+    threshold: i32,
+    required_hi: u64,
+    required_lo: u64,
+    rank_points: Option<[i32; 13]>,
+    min_first_points: i32,
+    forbidden_hi: u64,
+    forbidden_lo: u64,
+    max_melds: usize,
+    num_decks: u8,
+    num_jokers: u8,
+    joker_penalty: i32,
+    target_cards: u8,
+    meld_limit: usize,
+    max_jokers_per_meld: u8,
+    joker_bonus: Option<[i32; 2]>,
+) -> PyResult<CoverResult> {
+    let options = cover::CoverOptions {
+        enumeration: runs_sets::EnumOptions {
+            meld_limit,
+            max_jokers_per_meld,
+            ..enum_options(
+                ACE_LOW,
+                num_decks,
+                num_jokers,
+                None,
+                rank_points,
+                joker_bonus,
+            )?
+        },
+        min_first_points,
+        required_mask: bitset::merge_words(required_hi, required_lo),
+        forbidden_mask: bitset::merge_words(forbidden_hi, forbidden_lo),
+        max_melds,
+        joker_penalty,
+        target_cards,
+        ..Default::default()
+    };
+    check_joker_penalty(joker_penalty)?;
+    check_mask(mask_hi, mask_lo, &options.enumeration.deck)?;
+    check_mask(required_hi, required_lo, &options.enumeration.deck)?;
+    Ok(cover::best_cover_with(
+        mask_hi, mask_lo, objective, threshold, &options,
+    ))
+}
+
+#[pyfunction]
+fn best_discard(
+    mask_hi: u64,
+    mask_lo: u64,
+    objective: u8,
+    threshold: i32,
+) -> PyResult<(u8, CoverResult)> {
+    let options = cover::CoverOptions::default();
+    check_mask(mask_hi, mask_lo, &options.enumeration.deck)?;
+    cover::best_discard(mask_hi, mask_lo, objective, threshold, &options)
+        .ok_or_else(|| PyValueError::new_err("cannot discard from an empty hand"))
+}
+
+#[pyfunction]
+fn best_covers(
+    mask_hi: u64,
+    mask_lo: u64,
+    objective: u8,
+    threshold: i32,
+    k: usize,
+) -> PyResult<Vec<CoverResult>> {
+    check_mask(mask_hi, mask_lo, &DeckConfig::STANDARD)?;
+    Ok(cover::best_covers(
+        mask_hi, mask_lo, objective, threshold, k,
+    ))
+}
+
+#[pyfunction]
+fn best_cover_timed(
+    mask_hi: u64,
+    mask_lo: u64,
+    objective: u8,
+    threshold: i32,
+    time_budget_ms: u64,
+) -> PyResult<CoverResult> {
+    let options = cover::CoverOptions {
+        time_budget: Some(Duration::from_millis(time_budget_ms)),
+        ..Default::default()
+    };
+    check_mask(mask_hi, mask_lo, &options.enumeration.deck)?;
+    Ok(cover::best_cover_with(
+        mask_hi, mask_lo, objective, threshold, &options,
+    ))
+}
+
+#[pyfunction]
+fn best_cover_with_progress(
+    py: Python<'_>,
+    mask_hi: u64,
+    mask_lo: u64,
+    objective: u8,
+    threshold: i32,
+    callback: PyObject,
+) -> PyResult<CoverResult> {
+    check_mask(mask_hi, mask_lo, &DeckConfig::STANDARD)?;
+    // The search runs without the GIL and takes it back for each call; the
+    // first exception `callback` raises is re-raised once the search ends.
+    let error: Arc<Mutex<Option<PyErr>>> = Arc::default();
+    let on_progress: cover::ProgressCallback = {
+        let error = Arc::clone(&error);
+        Arc::new(move |covered_cards, total_points| {
+            Python::with_gil(|py| {
+                if let Err(err) = callback.call1(py, (covered_cards, total_points)) {
+                    error
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .get_or_insert(err);
+                }
+            })
+        })
+    };
+    let options = cover::CoverOptions {
+        on_progress: Some(on_progress),
+        ..Default::default()
+    };
+    let result = py
+        .allow_threads(|| cover::best_cover_with(mask_hi, mask_lo, objective, threshold, &options));
+    let raised = error.lock().unwrap_or_else(PoisonError::into_inner).take();
+    raised.map_or(Ok(result), Err)
+}
+
+#[pyfunction]
+fn best_cover_weighted(
+    mask_hi: u64,
+    mask_lo: u64,
+    card_weight: f64,
+    point_weight: f64,
+    joker_weight: f64,
+) -> PyResult<CoverResult> {
+    let weights = [card_weight, point_weight, joker_weight];
+    if weights
+        .iter()
+        .any(|weight| !weight.is_finite() || *weight < 0.0)
+    {
+        return Err(PyValueError::new_err(format!(
+            "weights {weights:?} must be finite and non-negative"
+        )));
+    }
+    check_mask(mask_hi, mask_lo, &DeckConfig::STANDARD)?;
+    Ok(cover::best_cover_weighted(
+        mask_hi,
+        mask_lo,
+        card_weight,
+        point_weight,
+        joker_weight,
+    ))
+}
+
+#[pyfunction]
+fn best_cover_delta(
+    prev_result: PyRef<'_, CoverResult>,
+    mask_hi: u64,
+    mask_lo: u64,
+    changed_id: u8,
+    objective: u8,
+    threshold: i32,
+) -> PyResult<CoverResult> {
+    let deck = DeckConfig::STANDARD;
+    check_mask(mask_hi, mask_lo, &deck)?;
+    if changed_id as usize >= deck.card_count() {
+        return Err(PyValueError::new_err(format!(
+            "card id {changed_id} is outside 0..{}",
+            deck.card_count()
+        )));
+    }
+    Ok(cover::best_cover_delta(
+        &prev_result,
+        mask_hi,
+        mask_lo,
+        changed_id,
+        objective,
+        threshold,
+    ))
+}
+
+#[pyfunction]
+fn all_maximal_covers(mask_hi: u64, mask_lo: u64, limit: usize) -> PyResult<Vec<CoverResult>> {
+    check_mask(mask_hi, mask_lo, &DeckConfig::STANDARD)?;
+    Ok(cover::all_maximal_covers(mask_hi, mask_lo, limit))
+}
+
+#[pyfunction]
+fn best_cover_pure(
+    mask_hi: u64,
+    mask_lo: u64,
+    objective: u8,
+    threshold: i32,
+) -> PyResult<CoverResult> {
+    check_mask(mask_hi, mask_lo, &DeckConfig::STANDARD)?;
+    Ok(cover::best_cover_pure(
+        mask_hi, mask_lo, objective, threshold,
+    ))
+}
+
+#[pyfunction]
+fn best_cover_joker_limited(
+    mask_hi: u64,
+    mask_lo: u64,
+    max_jokers: u8,
+    objective: u8,
+    threshold: i32,
+) -> PyResult<CoverResult> {
+    let options = cover::CoverOptions {
+        max_jokers: Some(max_jokers),
+        ..Default::default()
+    };
+    check_mask(mask_hi, mask_lo, &options.enumeration.deck)?;
+    Ok(cover::best_cover_with(
+        mask_hi, mask_lo, objective, threshold, &options,
+    ))
+}
+
+#[pyfunction]
+#[pyo3(signature = (mask_hi, mask_lo, max_jokers = u8::MAX))]
+fn min_deadwood(mask_hi: u64, mask_lo: u64, max_jokers: u8) -> PyResult<u8> {
+    check_mask(mask_hi, mask_lo, &DeckConfig::STANDARD)?;
+    Ok(cover::min_deadwood(mask_hi, mask_lo, max_jokers))
+}
+
+#[pyfunction]
+fn can_go_out(mask_hi: u64, mask_lo: u64) -> PyResult<bool> {
+    check_mask(mask_hi, mask_lo, &DeckConfig::STANDARD)?;
+    Ok(cover::can_go_out(mask_hi, mask_lo))
+}
+
+#[pyfunction]
+fn go_out_layout(mask_hi: u64, mask_lo: u64) -> PyResult<Option<CoverResult>> {
+    check_mask(mask_hi, mask_lo, &DeckConfig::STANDARD)?;
+    Ok(cover::go_out_layout(mask_hi, mask_lo))
+}
+
+#[pyfunction]
+fn rank_draws(
+    hand_hi: u64,
+    hand_lo: u64,
+    unseen_hi: u64,
+    unseen_lo: u64,
+    objective: u8,
+) -> PyResult<Vec<(u8, f64)>> {
+    let deck = DeckConfig::STANDARD;
+    check_mask(hand_hi, hand_lo, &deck)?;
+    check_mask(unseen_hi, unseen_lo, &deck)?;
+    Ok(cover::rank_draws(
+        hand_hi, hand_lo, unseen_hi, unseen_lo, objective,
+    ))
+}
+
+#[pyfunction]
+fn best_cover_dp(
+    py: Python<'_>,
+    mask_hi: u64,
+    mask_lo: u64,
+    objective: u8,
+    threshold: i32,
+) -> PyResult<CoverResult> {
+    check_mask(mask_hi, mask_lo, &DeckConfig::STANDARD)?;
+    Ok(py.allow_threads(|| cover::best_cover_dp(mask_hi, mask_lo, objective, threshold)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (discard_id, opponent_discards_hi, opponent_discards_lo, ace_mode = ACE_LOW))]
+fn discard_safety(
+    discard_id: u8,
+    opponent_discards_hi: u64,
+    opponent_discards_lo: u64,
+    ace_mode: u8,
+) -> PyResult<i32> {
+    let deck = DeckConfig::STANDARD;
+    check_mask(opponent_discards_hi, opponent_discards_lo, &deck)?;
+    if discard_id as usize >= deck.card_count() {
+        return Err(PyValueError::new_err(format!(
+            "card id {discard_id} is outside 0..{}",
+            deck.card_count()
+        )));
+    }
+    Ok(cover::discard_safety(
+        discard_id,
+        opponent_discards_hi,
+        opponent_discards_lo,
+        ace_mode,
+    ))
+}
+
+#[pyfunction]
+fn best_single_meld(mask_hi: u64, mask_lo: u64, objective: u8) -> PyResult<Option<Meld>> {
+    check_mask(mask_hi, mask_lo, &DeckConfig::STANDARD)?;
+    Ok(cover::best_single_meld(mask_hi, mask_lo, objective))
+}
+
+#[pyfunction]
+fn compare_covers(a: PyRef<'_, CoverResult>, b: PyRef<'_, CoverResult>, objective: u8) -> i8 {
+    cover::compare_covers(&a, &b, objective)
+}
+
+#[pyfunction]
+fn best_cover_parallel(
+    py: Python<'_>,
+    mask_hi: u64,
+    mask_lo: u64,
+    objective: u8,
+    threshold: i32,
+) -> PyResult<CoverResult> {
+    check_mask(mask_hi, mask_lo, &DeckConfig::STANDARD)?;
+    Ok(py.allow_threads(|| cover::best_cover_parallel(mask_hi, mask_lo, objective, threshold)))
+}
+
+#[pyfunction]
+fn best_cover_batch(
+    py: Python<'_>,
+    hands: Vec<(u64, u64)>,
+    objective: u8,
+    threshold: i32,
+) -> PyResult<Vec<CoverResult>> {
+    for &(mask_hi, mask_lo) in &hands {
+        check_mask(mask_hi, mask_lo, &DeckConfig::STANDARD)?;
+    }
+    Ok(py.allow_threads(|| cover::best_cover_batch(&hands, objective, threshold)))
+}
+
+#[pymodule]
+fn konkan_melds(_py: Python<'_>, module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(enumerate_melds, module)?)?;
+    module.add_function(wrap_pyfunction!(enumerate_melds_from_ids, module)?)?;
+    module.add_function(wrap_pyfunction!(enumerate_runs, module)?)?;
+    module.add_function(wrap_pyfunction!(enumerate_sets, module)?)?;
+    module.add_function(wrap_pyfunction!(enumerate_melds_stats, module)?)?;
+    module.add_function(wrap_pyfunction!(iter_melds, module)?)?;
+    module.add_function(wrap_pyfunction!(count_melds, module)?)?;
+    module.add_function(wrap_pyfunction!(classify_meld, module)?)?;
+    module.add_function(wrap_pyfunction!(validate_meld_with_jokers, module)?)?;
+    module.add_function(wrap_pyfunction!(melds_by_card, module)?)?;
+    module.add_function(wrap_pyfunction!(melds_containing, module)?)?;
+    module.add_function(wrap_pyfunction!(melds_gained_by_draw, module)?)?;
+    module.add_function(wrap_pyfunction!(near_runs, module)?)?;
+    module.add_function(wrap_pyfunction!(layoff_candidates, module)?)?;
+    module.add_function(wrap_pyfunction!(joker_swaps, module)?)?;
+    module.add_function(wrap_pyfunction!(rank_draws, module)?)?;
+    module.add_function(wrap_pyfunction!(best_cover, module)?)?;
+    module.add_function(wrap_pyfunction!(best_cover_constrained, module)?)?;
+    module.add_function(wrap_pyfunction!(best_cover_parallel, module)?)?;
+    module.add_function(wrap_pyfunction!(best_cover_dp, module)?)?;
+    module.add_function(wrap_pyfunction!(best_single_meld, module)?)?;
+    module.add_function(wrap_pyfunction!(compare_covers, module)?)?;
+    module.add_function(wrap_pyfunction!(best_cover_batch, module)?)?;
+    module.add_function(wrap_pyfunction!(best_cover_timed, module)?)?;
+    module.add_function(wrap_pyfunction!(best_cover_with_progress, module)?)?;
+    module.add_function(wrap_pyfunction!(best_cover_weighted, module)?)?;
+    module.add_function(wrap_pyfunction!(best_cover_joker_limited, module)?)?;
+    module.add_function(wrap_pyfunction!(best_cover_pure, module)?)?;
+    module.add_function(wrap_pyfunction!(best_cover_delta, module)?)?;
+    module.add_function(wrap_pyfunction!(best_covers, module)?)?;
+    module.add_function(wrap_pyfunction!(all_maximal_covers, module)?)?;
+    module.add_function(wrap_pyfunction!(best_discard, module)?)?;
+    module.add_function(wrap_pyfunction!(discard_safety, module)?)?;
+    module.add_function(wrap_pyfunction!(min_deadwood, module)?)?;
+    module.add_function(wrap_pyfunction!(can_go_out, module)?)?;
+    module.add_function(wrap_pyfunction!(go_out_layout, module)?)?;
+    module.add_function(wrap_pyfunction!(build_mask, module)?)?;
+    module.add_function(wrap_pyfunction!(mask_to_cards, module)?)?;
+    module.add_function(wrap_pyfunction!(union_hands, module)?)?;
+    module.add_function(wrap_pyfunction!(melds_overlap, module)?)?;
+    module.add_function(wrap_pyfunction!(melds_disjoint_all, module)?)?;
+    module.add_function(wrap_pyfunction!(meld_to_bytes, module)?)?;
+    module.add_function(wrap_pyfunction!(meld_from_bytes, module)?)?;
+    module.add_function(wrap_pyfunction!(cover_to_bytes, module)?)?;
+    module.add_function(wrap_pyfunction!(cover_from_bytes, module)?)?;
+    module.add_function(wrap_pyfunction!(canonical_hand_key, module)?)?;
+    module.add_function(wrap_pyfunction!(deadwood_points, module)?)?;
+    module.add_function(wrap_pyfunction!(deadwood_breakdown, module)?)?;
+    module.add_function(wrap_pyfunction!(parse_card, module)?)?;
+    module.add_function(wrap_pyfunction!(parse_hand, module)?)?;
+    module.add_function(wrap_pyfunction!(card_to_string, module)?)?;
+    module.add_function(wrap_pyfunction!(mask_to_strings, module)?)?;
+    module.add_class::<Meld>()?;
+    module.add_class::<CoverResult>()?;
+    module.add_class::<MeldIterator>()?;
+    module.add_class::<EnumStats>()?;
+    module.add_class::<Solver>()?;
+    module.add("OBJ_MAX_CARDS", OBJ_MAX_CARDS)?;
+    module.add("OBJ_MIN_DEADWOOD", OBJ_MIN_DEADWOOD)?;
+    module.add("OBJ_FIRST_14", OBJ_FIRST_14)?;
+    module.add("OBJ_MAX_POINTS", OBJ_MAX_POINTS)?;
+    module.add("OBJ_MIN_JOKERS", OBJ_MIN_JOKERS)?;
+    module.add("OBJ_MIN_DEADWOOD_SOFT", OBJ_MIN_DEADWOOD_SOFT)?;
+    module.add("OBJ_WEIGHTED", OBJ_WEIGHTED)?;
+    module.add("OBJ_MAX_CARDS_SAFE", OBJ_MAX_CARDS_SAFE)?;
+    module.add("KIND_SET", KIND_SET)?;
+    module.add("KIND_RUN", KIND_RUN)?;
+    module.add("KIND_PARTIAL", KIND_PARTIAL)?;
+    Ok(())
+}
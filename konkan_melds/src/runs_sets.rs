@@ -1,50 +1,184 @@
 //! Enumeration of Konkan meld candidates (runs and sets).
 
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 
-use crate::bitset::{card_bitmask, combine_mask};
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+use crate::bitset::{build_mask, card_bitmask, combine_mask, mask_cards, merge_words};
 use crate::deck::{
-    collect_cards, decode_card, points_for_rank, CardInfo, KIND_RUN, KIND_SET, NUM_RANKS, NUM_SUITS,
+    collect_grouped, collect_grouped_into, decode_card, CardInfo, DeckConfig, Scoring,
+    ACE_HIGH_LOW, ACE_LOW, ACE_WRAP, CARDS_PER_DECK, KIND_PARTIAL, KIND_RUN, KIND_SET, NUM_RANKS,
+    NUM_SUITS,
 };
-use crate::Meld;
+use crate::notation::{card_to_string, RANK_TOKENS};
+use crate::{EnumStats, Meld, ORDER_CARDS, ORDER_MASK, ORDER_POINTS};
 
-fn cartesian_product(lists: &[&[u8]]) -> Vec<Vec<u8>> {
-    if lists.is_empty() {
-        return vec![Vec::new()];
+/// Default `EnumOptions::meld_limit`, far above the melds any real hand holds.
+pub const DEFAULT_MELD_LIMIT: usize = 100_000;
+
+/// Rules that shape which melds `enumerate_melds` produces.
+#[derive(Clone, Copy)]
+pub struct EnumOptions {
+    pub ace_mode: u8,
+    pub deck: DeckConfig,
+    /// Point values, which must pass `Scoring::fits_points`.
+    pub scoring: Scoring,
+    /// Emit only runs that no natural card of their suit could lengthen by a
+    /// rank at either end. The cover search needs the shorter runs too, so
+    /// this stays off there.
+    pub only_maximal_runs: bool,
+    /// Longest run to emit, for house rules that cap run length; 0 means no
+    /// limit. The walk stops there too, which bounds its cost.
+    pub max_run_len: usize,
+    /// Order of the `enumerate_melds` output: `ORDER_MASK` (the canonical
+    /// `Meld::sort_key` order), `ORDER_POINTS` (most points first, then fewer
+    /// jokers) or `ORDER_CARDS` (most cards first). Ties keep mask order.
+    pub order: u8,
+    /// Build runs from the lowest-ID copy of each card only, so two decks
+    /// yield one 7-8-9 of spades rather than one per combination of copies.
+    /// Jokers are left as they are. Two runs of the same ranks can then no
+    /// longer be melded side by side, so the cover search leaves this off.
+    pub collapse_copies: bool,
+    /// Emit one set per natural cards and joker count, using the lowest
+    /// joker IDs, rather than one per choice of printed jokers. Two sets can
+    /// then no longer each take a different joker, so the cover search
+    /// leaves this off. Runs are unaffected.
+    ///
+    /// Each choice of jokers has its own mask, so `seen_masks` never merges
+    /// them; the extra assignments are dropped before they reach it. Leave
+    /// this off when `Scoring::joker_bonus` tells the jokers apart, or the
+    /// sets keep the lowest IDs whatever those are worth.
+    pub dedup_joker_assignments: bool,
+    /// Most melds `enumerate_melds` returns, so no hand can make it allocate
+    /// without bound. Melds past the limit are dropped in generation order,
+    /// sets by rank before runs by suit, and the enumeration reports itself
+    /// truncated. The lazy `MeldIterator` ignores it.
+    pub meld_limit: usize,
+    /// Most printed jokers one meld may hold, for house rules allowing a
+    /// single joker per meld; `u8::MAX` leaves it unlimited. Wild cards do
+    /// not count against it.
+    pub max_jokers_per_meld: u8,
+    /// Also list pairs of natural cards one card short of a meld, as
+    /// `KIND_PARTIAL` melds, for showing players what they nearly hold. The
+    /// cover search never selects them.
+    pub include_partials: bool,
+}
+
+impl Default for EnumOptions {
+    fn default() -> Self {
+        EnumOptions {
+            ace_mode: ACE_LOW,
+            deck: DeckConfig::STANDARD,
+            scoring: Scoring::STANDARD,
+            only_maximal_runs: false,
+            max_run_len: 0,
+            order: ORDER_MASK,
+            collapse_copies: false,
+            dedup_joker_assignments: false,
+            meld_limit: DEFAULT_MELD_LIMIT,
+            max_jokers_per_meld: u8::MAX,
+            include_partials: false,
+        }
     }
-    let mut results = Vec::new();
-    let mut current = Vec::with_capacity(lists.len());
-    fn recurse(
-        lists: &[&[u8]],
-        index: usize,
-        current: &mut Vec<u8>,
-        results: &mut Vec<Vec<u8>>,
-    ) {
-        if index == lists.len() {
-            results.push(current.clone());
-            return;
+}
+
+/// Masks the running generator has already emitted. Each generator clears
+/// the map it uses, so one `SeenMasks` serves every generator and hand in
+/// turn without allocating again.
+#[derive(Default)]
+struct SeenMasks {
+    sets: HashSet<u128>,
+    /// Index of each run's mask among the collected melds.
+    runs: HashMap<u128, usize>,
+}
+
+/// Where the generators send each distinct meld they find.
+enum MeldSink {
+    Collect {
+        melds: Vec<Meld>,
+        /// Candidates dropped because a meld with the same cards was found.
+        duplicates: usize,
+        /// Most melds to keep; later ones are dropped unbuilt.
+        limit: usize,
+        /// A meld was dropped for `limit`.
+        truncated: bool,
+    },
+    /// Only the number of melds, for callers that never look at them.
+    Count(usize),
+}
+
+impl MeldSink {
+    fn collecting() -> Self {
+        MeldSink::collecting_up_to(usize::MAX)
+    }
+
+    fn collecting_up_to(limit: usize) -> Self {
+        MeldSink::Collect {
+            melds: Vec::new(),
+            duplicates: 0,
+            limit,
+            truncated: false,
         }
-        for &value in lists[index] {
-            current.push(value);
-            recurse(lists, index + 1, current, results);
-            current.pop();
+    }
+
+    /// Records one more meld; `build` only runs when melds are collected and
+    /// the limit leaves room for it.
+    fn push(&mut self, build: impl FnOnce() -> Meld) {
+        match self {
+            MeldSink::Collect {
+                melds,
+                limit,
+                truncated,
+                ..
+            } => {
+                if melds.len() < *limit {
+                    melds.push(build());
+                } else {
+                    *truncated = true;
+                }
+            }
+            MeldSink::Count(count) => *count += 1,
+        }
+    }
+
+    fn truncated(&self) -> bool {
+        matches!(
+            self,
+            MeldSink::Collect {
+                truncated: true,
+                ..
+            }
+        )
+    }
+
+    /// Notes a candidate whose cards an earlier meld already holds.
+    fn duplicate(&mut self) {
+        if let MeldSink::Collect { duplicates, .. } = self {
+            *duplicates += 1;
+        }
+    }
+
+    fn into_melds(self) -> Vec<Meld> {
+        match self {
+            MeldSink::Collect { melds, .. } => melds,
+            MeldSink::Count(_) => unreachable!("collecting sink"),
         }
     }
-    recurse(lists, 0, &mut current, &mut results);
-    results
 }
 
-fn joker_combinations(jokers: &[u8], k: usize) -> Vec<Vec<u8>> {
+fn combinations(items: &[u8], k: usize) -> Vec<Vec<u8>> {
     if k == 0 {
         return vec![Vec::new()];
     }
-    if jokers.len() < k {
+    if items.len() < k {
         return Vec::new();
     }
     let mut results = Vec::new();
     let mut current = Vec::with_capacity(k);
     fn recurse(
-        jokers: &[u8],
+        items: &[u8],
         start: usize,
         k: usize,
         current: &mut Vec<u8>,
@@ -54,179 +188,1765 @@ fn joker_combinations(jokers: &[u8], k: usize) -> Vec<Vec<u8>> {
             results.push(current.clone());
             return;
         }
-        for idx in start..jokers.len() {
-            current.push(jokers[idx]);
-            recurse(jokers, idx + 1, k, current, results);
+        for idx in start..items.len() {
+            current.push(items[idx]);
+            recurse(items, idx + 1, k, current, results);
             current.pop();
         }
     }
-    recurse(jokers, 0, k, &mut current, &mut results);
+    recurse(items, 0, k, &mut current, &mut results);
     results
 }
 
-fn enumerate_sets(cards: &[CardInfo], jokers: &[u8]) -> Vec<Meld> {
-    let mut by_rank = vec![vec![Vec::<u8>::new(); NUM_SUITS]; NUM_RANKS];
-    for card in cards {
-        if let (Some(rank), Some(suit)) = (card.rank, card.suit) {
-            by_rank[rank as usize][suit as usize].push(card.id);
-        }
+/// Sets of a single rank, sized from `DeckConfig::min_set_size` to
+/// `max_set_size`; `suit_lists` holds that rank's card IDs per suit.
+///
+/// With two decks both copies of a suit may appear in the same set
+/// (K♠ K♠ K♥), so the natural cards are chosen from every copy held. Open
+/// slots are filled from the printed `jokers` and then the wild cards, which
+/// the caller leaves out when this rank is the wild rank itself.
+fn rank_sets(
+    rank: usize,
+    suit_lists: &[Vec<u8>],
+    jokers: &[u8],
+    wilds: &[u8],
+    options: &EnumOptions,
+    seen_masks: &mut HashSet<u128>,
+    sink: &mut MeldSink,
+) {
+    let scoring = &options.scoring;
+    let deck = &options.deck;
+    let naturals: Vec<u8> = suit_lists.iter().flatten().copied().collect();
+    let substitutes: Vec<u8> = jokers.iter().chain(wilds).copied().collect();
+    seen_masks.clear();
+    let max_size = deck.max_set_size as usize;
+    if (deck.min_set_size as usize) > max_size || naturals.is_empty() {
+        return;
     }
 
-    let mut results = Vec::new();
-    let mut seen_masks: HashSet<u128> = HashSet::new();
-    let max_jokers = jokers.len();
-
-    for rank in 0..NUM_RANKS {
-        let suit_lists = &by_rank[rank];
-        for target_size in 3..=4 {
-            for subset_mask in 1usize..(1 << NUM_SUITS) {
-                let actual_count = subset_mask.count_ones() as usize;
-                if actual_count == 0 || actual_count > target_size {
-                    continue;
-                }
-                let mut lists: Vec<&[u8]> = Vec::with_capacity(actual_count);
-                let mut valid = true;
-                for suit in 0..NUM_SUITS {
-                    if (subset_mask & (1 << suit)) == 0 {
-                        continue;
-                    }
-                    if suit_lists[suit].is_empty() {
-                        valid = false;
-                        break;
-                    }
-                    lists.push(suit_lists[suit].as_slice());
-                }
-                if !valid {
-                    continue;
-                }
-                let jokers_needed = target_size - actual_count;
-                if jokers_needed > max_jokers {
-                    continue;
-                }
+    // Every set size draws on the same combinations, so each count is built
+    // once, with its mask, rather than once per size; the product below then
+    // joins two masks per set instead of walking both card lists.
+    let combo_mask = |combo: &[u8]| {
+        combo
+            .iter()
+            .fold(0u128, |mask, &card_id| mask | card_bitmask(card_id))
+    };
+    let natural_masks: Vec<Vec<u128>> = (0..=max_size.min(naturals.len()))
+        .map(|count| {
+            combinations(&naturals, count)
+                .iter()
+                .map(|combo| combo_mask(combo))
+                .collect()
+        })
+        .collect();
+    let substitute_combos: Vec<Vec<(Vec<u8>, u128)>> = (0..=max_size.min(substitutes.len()))
+        .map(|count| {
+            let mut combos = combinations(&substitutes, count);
+            // Combinations keep the jokers first and in order, so the lowest
+            // joker IDs are those leading `jokers` itself.
+            combos.retain(|combo| {
+                let picked = combo.iter().filter(|id| jokers.contains(id)).count();
+                picked <= options.max_jokers_per_meld as usize
+                    && (!options.dedup_joker_assignments || combo[..picked] == jokers[..picked])
+            });
+            combos
+                .into_iter()
+                .map(|combo| {
+                    let mask = combo_mask(&combo);
+                    (combo, mask)
+                })
+                .collect()
+        })
+        .collect();
 
-                let actual_combos = cartesian_product(&lists);
-                let joker_combos = joker_combinations(jokers, jokers_needed);
+    for target_size in deck.min_set_size as usize..=max_size {
+        let by_count = natural_masks.iter().enumerate().take(target_size + 1);
+        for (natural_count, masks) in by_count.skip(1) {
+            let substitutes_needed = target_size - natural_count;
+            let Some(substitute_combos) = substitute_combos.get(substitutes_needed) else {
+                continue;
+            };
 
-                for actual_cards in &actual_combos {
-                    for joker_cards in &joker_combos {
-                        let mut mask: u128 = 0;
-                        for &card_id in actual_cards {
-                            mask |= card_bitmask(card_id);
-                        }
-                        for &joker_id in joker_cards {
-                            mask |= card_bitmask(joker_id);
-                        }
-                        if seen_masks.insert(mask) {
-                            let (mask_hi, mask_lo) = combine_mask(mask);
-                            let points = points_for_rank(rank as u8) * target_size as i32;
-                            results.push(Meld {
-                                mask_hi,
-                                mask_lo,
-                                points,
-                                jokers_used: jokers_needed as u8,
-                                kind: KIND_SET,
-                            });
-                        }
+            for &natural_mask in masks {
+                for (substitute_cards, substitute_mask) in substitute_combos {
+                    // Naturals and substitutes never share a card and no two
+                    // combinations of either share a mask, so this is only a
+                    // last guard.
+                    let mask = natural_mask | substitute_mask;
+                    if !seen_masks.insert(mask) {
+                        sink.duplicate();
+                        continue;
                     }
+                    sink.push(|| {
+                        let joker_ids: Vec<u8> = substitute_cards
+                            .iter()
+                            .copied()
+                            .filter(|card_id| jokers.contains(card_id))
+                            .collect();
+                        let jokers_used = joker_ids.len();
+                        let wilds_used = substitutes_needed - jokers_used;
+                        let (mask_hi, mask_lo) = combine_mask(mask);
+                        let rank_points = scoring.set_points(rank as u8);
+                        let points_by_card: Vec<(u8, i32)> = mask_cards(mask)
+                            .into_iter()
+                            .map(|card_id| {
+                                if joker_ids.contains(&card_id) {
+                                    (card_id, scoring.joker_points(card_id, deck, rank_points))
+                                } else {
+                                    (card_id, rank_points)
+                                }
+                            })
+                            .collect();
+                        let points = points_by_card
+                            .iter()
+                            .map(|&(_, points)| points as i64)
+                            .sum();
+                        Meld {
+                            mask_hi,
+                            mask_lo,
+                            points: meld_points(points),
+                            jokers_used: jokers_used as u8,
+                            wilds_used: wilds_used as u8,
+                            kind: KIND_SET,
+                            is_pure: substitutes_needed == 0,
+                            points_by_card,
+                            joker_ids,
+                            cards_needed: 0,
+                        }
+                    });
                 }
             }
         }
     }
+}
 
-    results
+/// Narrows points summed as `i64` to `Meld::points`.
+///
+/// # Panics
+///
+/// For a meld past the `i32` range, which no table passing
+/// `Scoring::fits_points` can score.
+fn meld_points(points: i64) -> i32 {
+    i32::try_from(points).expect("Scoring::fits_points keeps meld points within i32")
+}
+
+/// Inputs shared by every step of the run walk within one suit.
+struct RunContext<'a> {
+    rank_lists: &'a [Vec<u8>],
+    jokers: &'a [u8],
+    /// Wild cards of any suit, tracked after `jokers` in the in-use bits.
+    wilds: &'a [u8],
+    scoring: &'a Scoring,
+    deck: &'a DeckConfig,
+    /// One past the last rank position the run may reach.
+    end: usize,
+    /// Ace-wrap mode: a run may continue below the ace from the king.
+    wraps: bool,
+    /// Most cards a run may hold; 0 means no limit.
+    max_len: usize,
+    /// Most printed jokers a run may hold.
+    max_jokers: u8,
+    only_maximal: bool,
 }
 
 fn explore_run(
-    rank_lists: &[Vec<u8>],
-    current_rank: usize,
+    ctx: &RunContext,
+    position: usize,
     current_cards: &mut Vec<u8>,
-    seen_masks: &mut HashSet<u128>,
-    results: &mut Vec<Meld>,
+    current_points: i64,
+    substitutes_in_use: u128,
+    seen_masks: &mut HashMap<u128, usize>,
+    sink: &mut MeldSink,
+) {
+    if position >= ctx.end || !ctx.has_room(current_cards.len()) {
+        return;
+    }
+    // Positions past the king continue from the ace again (ace-high or wrapping runs).
+    let rank = position % NUM_RANKS;
+    let rank_points = ctx.scoring.run_points(position) as i64;
+
+    for &card_id in &ctx.rank_lists[rank] {
+        // A wild card may already fill an earlier slot of this run.
+        if current_cards.contains(&card_id) {
+            continue;
+        }
+        current_cards.push(card_id);
+        emit_run(
+            ctx,
+            position,
+            current_cards,
+            current_points + rank_points,
+            substitutes_in_use,
+            seen_masks,
+            sink,
+        );
+        explore_run(
+            ctx,
+            position + 1,
+            current_cards,
+            current_points + rank_points,
+            substitutes_in_use,
+            seen_masks,
+            sink,
+        );
+        current_cards.pop();
+    }
+
+    // A joker or wild card may stand in for this rank, at most once per
+    // physical card. Substitutes are tracked by index, jokers first; a hand
+    // holds at most 128 card IDs.
+    let jokers_in_use = (substitutes_in_use & ((1u128 << ctx.jokers.len()) - 1)).count_ones();
+    for (substitute_idx, &substitute_id) in ctx.jokers.iter().chain(ctx.wilds).enumerate() {
+        let substitute_bit = 1u128 << substitute_idx;
+        if substitutes_in_use & substitute_bit != 0 || current_cards.contains(&substitute_id) {
+            continue;
+        }
+        let is_joker = substitute_idx < ctx.jokers.len();
+        if is_joker && jokers_in_use >= ctx.max_jokers as u32 {
+            continue;
+        }
+        let slot_points = if is_joker {
+            let slot_points = ctx.scoring.run_points(position);
+            ctx.scoring
+                .joker_points(substitute_id, ctx.deck, slot_points) as i64
+        } else {
+            rank_points
+        };
+        current_cards.push(substitute_id);
+        emit_run(
+            ctx,
+            position,
+            current_cards,
+            current_points + slot_points,
+            substitutes_in_use | substitute_bit,
+            seen_masks,
+            sink,
+        );
+        explore_run(
+            ctx,
+            position + 1,
+            current_cards,
+            current_points + slot_points,
+            substitutes_in_use | substitute_bit,
+            seen_masks,
+            sink,
+        );
+        current_cards.pop();
+    }
+}
+
+impl RunContext<'_> {
+    /// Whether a run of `len` cards may take one more.
+    fn has_room(&self, len: usize) -> bool {
+        self.max_len == 0 || len < self.max_len
+    }
+}
+
+/// Whether a natural card of the suit could lengthen the run ending at
+/// `position` by one rank at either end.
+fn run_extends(ctx: &RunContext, position: usize, current_cards: &[u8]) -> bool {
+    if !ctx.has_room(current_cards.len()) {
+        return false;
+    }
+    let first_position = position + 1 - current_cards.len();
+    let free_at = |position: usize| {
+        ctx.rank_lists[position % NUM_RANKS]
+            .iter()
+            .any(|card_id| !current_cards.contains(card_id))
+    };
+    if ctx.wraps {
+        current_cards.len() <= NUM_RANKS
+            && (free_at(position + 1) || free_at(first_position + NUM_RANKS - 1))
+    } else {
+        (position + 1 < ctx.end && free_at(position + 1))
+            || (first_position > 0 && free_at(first_position - 1))
+    }
+}
+
+/// Records the run ending at `position` unless the same cards already form
+/// a run scoring at least as much with no more wild cards.
+fn emit_run(
+    ctx: &RunContext,
+    position: usize,
+    current_cards: &[u8],
+    points: i64,
+    substitutes_in_use: u128,
+    seen_masks: &mut HashMap<u128, usize>,
+    sink: &mut MeldSink,
 ) {
-    if current_rank >= NUM_RANKS {
+    if current_cards.len() < 3
+        || current_cards
+            .iter()
+            .all(|card_id| ctx.jokers.contains(card_id) || ctx.wilds.contains(card_id))
+    {
         return;
     }
-    let cards = &rank_lists[current_rank];
-    if cards.is_empty() {
+    if ctx.only_maximal && run_extends(ctx, position, current_cards) {
         return;
     }
-    for &card_id in cards {
-        current_cards.push(card_id);
-        if current_cards.len() >= 3 {
-            let mut mask: u128 = 0;
-            let mut points: i32 = 0;
-            for &cid in current_cards.iter() {
-                mask |= card_bitmask(cid);
-                let info = decode_card(cid);
-                if let Some(rank) = info.rank {
-                    points += points_for_rank(rank);
+    let substitutes_used = substitutes_in_use.count_ones() as usize;
+    let wilds_used = (substitutes_in_use >> ctx.jokers.len()).count_ones() as u8;
+    let jokers_used = (substitutes_used - wilds_used as usize) as u8;
+    let mut mask: u128 = 0;
+    for &cid in current_cards {
+        mask |= card_bitmask(cid);
+    }
+    // A count only needs the distinct masks, not which reading wins.
+    let (results, duplicates, limit, truncated) = match sink {
+        MeldSink::Collect {
+            melds,
+            duplicates,
+            limit,
+            truncated,
+        } => (melds, duplicates, *limit, truncated),
+        MeldSink::Count(count) => {
+            if seen_masks.insert(mask, 0).is_none() {
+                *count += 1;
+            }
+            return;
+        }
+    };
+    // The same cards can form several runs when a joker sits at either end
+    // (5-6-J reads as 4-5-6 or 5-6-7), or when a wild card could also sit in
+    // its natural slot; keep the highest scoring reading, then the one
+    // spending fewer wild cards.
+    let points = meld_points(points);
+    let existing = seen_masks.get(&mask).copied();
+    if existing.is_some() {
+        *duplicates += 1;
+    }
+    if existing.is_some_and(|existing| {
+        let meld = &results[existing];
+        (meld.points, Reverse(meld.wilds_used)) >= (points, Reverse(wilds_used))
+    }) {
+        return;
+    }
+    if existing.is_none() && results.len() >= limit {
+        *truncated = true;
+        return;
+    }
+    // Each card, joker or natural, is worth the rank of the slot it fills,
+    // unless the scoring leaves jokers out.
+    let first_position = position + 1 - current_cards.len();
+    let mut points_by_card: Vec<(u8, i32)> = current_cards
+        .iter()
+        .enumerate()
+        .map(|(offset, &card_id)| {
+            let slot_points = ctx.scoring.run_points(first_position + offset);
+            if ctx.jokers.contains(&card_id) {
+                (
+                    card_id,
+                    ctx.scoring.joker_points(card_id, ctx.deck, slot_points),
+                )
+            } else {
+                (card_id, slot_points)
+            }
+        })
+        .collect();
+    points_by_card.sort_unstable();
+    if let Some(existing) = existing {
+        let meld = &mut results[existing];
+        meld.points = points;
+        meld.wilds_used = wilds_used;
+        meld.is_pure = substitutes_used == 0;
+        meld.points_by_card = points_by_card;
+        return;
+    }
+    seen_masks.insert(mask, results.len());
+    let (mask_hi, mask_lo) = combine_mask(mask);
+    let mut joker_ids: Vec<u8> = current_cards
+        .iter()
+        .copied()
+        .filter(|card_id| ctx.jokers.contains(card_id))
+        .collect();
+    joker_ids.sort_unstable();
+    results.push(Meld {
+        mask_hi,
+        mask_lo,
+        points,
+        jokers_used,
+        wilds_used,
+        kind: KIND_RUN,
+        is_pure: substitutes_used == 0,
+        points_by_card,
+        joker_ids,
+        cards_needed: 0,
+    });
+}
+
+fn run_positions(ace_mode: u8, start: usize) -> usize {
+    match ace_mode {
+        ACE_HIGH_LOW => NUM_RANKS + 1,
+        ACE_WRAP => start + NUM_RANKS + 1,
+        _ => NUM_RANKS,
+    }
+}
+
+/// Runs within a single suit; `rank_lists` holds that suit's card IDs per rank.
+/// Every run needs a natural card that is not wild, so its mask pins the suit
+/// and the readings deduplicated through `seen_masks` never span two suits.
+fn suit_runs(
+    rank_lists: &[Vec<u8>],
+    jokers: &[u8],
+    wilds: &[u8],
+    options: &EnumOptions,
+    seen_masks: &mut HashMap<u128, usize>,
+    sink: &mut MeldSink,
+) {
+    if rank_lists.iter().all(|cards| cards.is_empty()) {
+        return;
+    }
+    let collapsed: Vec<Vec<u8>>;
+    let rank_lists = if options.collapse_copies {
+        // The lists are in ID order, so the first card is the lowest copy.
+        collapsed = rank_lists
+            .iter()
+            .map(|cards| cards.iter().take(1).copied().collect())
+            .collect();
+        &collapsed
+    } else {
+        rank_lists
+    };
+    if jokers.is_empty() && wilds.is_empty() && rank_lists.iter().all(|cards| cards.len() <= 1) {
+        single_copy_runs(rank_lists, options, seen_masks, sink);
+    } else {
+        walked_runs(rank_lists, jokers, wilds, options, seen_masks, sink);
+    }
+}
+
+fn run_context<'a>(
+    rank_lists: &'a [Vec<u8>],
+    jokers: &'a [u8],
+    wilds: &'a [u8],
+    options: &'a EnumOptions,
+    start: usize,
+) -> RunContext<'a> {
+    RunContext {
+        rank_lists,
+        jokers,
+        wilds,
+        scoring: &options.scoring,
+        deck: &options.deck,
+        end: run_positions(options.ace_mode, start),
+        wraps: options.ace_mode == ACE_WRAP,
+        max_len: options.max_run_len,
+        max_jokers: options.max_jokers_per_meld,
+        only_maximal: options.only_maximal_runs,
+    }
+}
+
+/// `suit_runs` by walking every card, joker and wild card that could fill
+/// each rank in turn.
+fn walked_runs(
+    rank_lists: &[Vec<u8>],
+    jokers: &[u8],
+    wilds: &[u8],
+    options: &EnumOptions,
+    seen_masks: &mut HashMap<u128, usize>,
+    sink: &mut MeldSink,
+) {
+    seen_masks.clear();
+    for (start, start_cards) in rank_lists.iter().enumerate() {
+        if start_cards.is_empty() && jokers.is_empty() && wilds.is_empty() {
+            continue;
+        }
+        let ctx = run_context(rank_lists, jokers, wilds, options, start);
+        let mut current_cards = Vec::new();
+        explore_run(&ctx, start, &mut current_cards, 0, 0, seen_masks, sink);
+    }
+}
+
+/// `suit_runs` for a suit holding at most one card of each rank and no
+/// joker or wild card to fill a gap. Each run is then a prefix of the span
+/// of held ranks starting at its first card, read off a rank bitmask, and
+/// the runs come out in the same order `walked_runs` finds them.
+fn single_copy_runs(
+    rank_lists: &[Vec<u8>],
+    options: &EnumOptions,
+    seen_masks: &mut HashMap<u128, usize>,
+    sink: &mut MeldSink,
+) {
+    let rank_bits = rank_lists
+        .iter()
+        .enumerate()
+        .filter(|(_, cards)| !cards.is_empty())
+        .fold(0u64, |bits, (rank, _)| bits | 1 << rank);
+    // Positions past the king repeat the ranks from the ace.
+    let position_bits = rank_bits | rank_bits << NUM_RANKS | rank_bits << (2 * NUM_RANKS);
+    seen_masks.clear();
+    let mut current_cards = Vec::with_capacity(NUM_RANKS);
+    for start in 0..NUM_RANKS {
+        if rank_bits & 1 << start == 0 {
+            continue;
+        }
+        let ctx = run_context(rank_lists, &[], &[], options, start);
+        // A run holds each card once, so it stops after a full turn of the suit.
+        let mut span = ((position_bits >> start).trailing_ones() as usize)
+            .min(ctx.end - start)
+            .min(NUM_RANKS);
+        if ctx.max_len > 0 {
+            span = span.min(ctx.max_len);
+        }
+        current_cards.clear();
+        let mut points = 0;
+        for position in start..start + span {
+            let rank = position % NUM_RANKS;
+            current_cards.push(rank_lists[rank][0]);
+            points += ctx.scoring.run_points(position) as i64;
+            emit_run(&ctx, position, &current_cards, points, 0, seen_masks, sink);
+        }
+    }
+}
+
+/// Produces melds on demand, one rank's sets or one suit's runs at a time.
+///
+/// Melds come out grouped by generator (sets by rank, then runs by suit)
+/// rather than in `Meld::sort_key` order; collect and sort when order matters.
+#[cfg_attr(feature = "python", pyclass)]
+pub struct MeldIterator {
+    by_rank: Vec<Vec<Vec<u8>>>,
+    per_suit: Vec<Vec<Vec<u8>>>,
+    jokers: Vec<u8>,
+    /// Cards of the deck's wild rank, each also usable as a substitute.
+    wilds: Vec<u8>,
+    options: EnumOptions,
+    /// Next generator to run: ranks `0..NUM_RANKS`, then one per suit.
+    stage: usize,
+    pending: std::vec::IntoIter<Meld>,
+}
+
+impl MeldIterator {
+    pub fn new(mask_hi: u64, mask_lo: u64, options: &EnumOptions) -> Self {
+        let mut iter = MeldIterator {
+            by_rank: vec![vec![Vec::new(); NUM_SUITS]; NUM_RANKS],
+            per_suit: vec![vec![Vec::new(); NUM_RANKS]; NUM_SUITS],
+            jokers: Vec::new(),
+            wilds: Vec::new(),
+            options: *options,
+            stage: 0,
+            pending: Vec::new().into_iter(),
+        };
+        iter.refill(mask_hi, mask_lo);
+        iter
+    }
+
+    /// Starts over on another hand, emptying the buckets without giving up
+    /// their allocations.
+    fn refill(&mut self, mask_hi: u64, mask_lo: u64) {
+        collect_grouped_into(
+            mask_hi,
+            mask_lo,
+            &self.options.deck,
+            &mut self.by_rank,
+            &mut self.jokers,
+        );
+        for bucket in self.per_suit.iter_mut().flatten() {
+            bucket.clear();
+        }
+        self.wilds.clear();
+        for (rank, suits) in self.by_rank.iter().enumerate() {
+            for (suit, ids) in suits.iter().enumerate() {
+                self.per_suit[suit][rank].extend(ids);
+            }
+            if self.options.deck.wild_rank == Some(rank as u8) {
+                self.wilds.extend(suits.iter().flatten());
+            }
+        }
+        // The wild rank's cards were gathered suit by suit, not in ID order.
+        self.wilds.sort_unstable();
+        self.stage = 0;
+        self.pending = Vec::new().into_iter();
+    }
+
+    /// Files a card into its buckets, keeping every bucket in ID order.
+    fn insert(&mut self, card: &CardInfo) {
+        fn insert_sorted(ids: &mut Vec<u8>, id: u8) {
+            let pos = ids.partition_point(|&existing| existing < id);
+            ids.insert(pos, id);
+        }
+        let (Some(rank), Some(suit)) = (card.rank, card.suit) else {
+            insert_sorted(&mut self.jokers, card.id);
+            return;
+        };
+        if self.options.deck.is_wild(card) {
+            insert_sorted(&mut self.wilds, card.id);
+        }
+        insert_sorted(&mut self.by_rank[rank as usize][suit as usize], card.id);
+        insert_sorted(&mut self.per_suit[suit as usize][rank as usize], card.id);
+    }
+
+    /// A fresh iterator over this hand plus `card`.
+    fn with_card(&self, card: &CardInfo) -> Self {
+        let mut iter = MeldIterator {
+            by_rank: self.by_rank.clone(),
+            per_suit: self.per_suit.clone(),
+            jokers: self.jokers.clone(),
+            wilds: self.wilds.clone(),
+            options: self.options,
+            stage: 0,
+            pending: Vec::new().into_iter(),
+        };
+        iter.insert(card);
+        iter
+    }
+
+    fn generate(&self, stage: usize) -> Vec<Meld> {
+        let mut sink = MeldSink::collecting();
+        self.generate_into(stage, &mut SeenMasks::default(), &mut sink);
+        sink.into_melds()
+    }
+
+    fn generate_into(&self, stage: usize, seen: &mut SeenMasks, sink: &mut MeldSink) {
+        if stage < NUM_RANKS {
+            // A set of the wild rank holds its wild cards as naturals.
+            let wilds: &[u8] = if self.options.deck.wild_rank == Some(stage as u8) {
+                &[]
+            } else {
+                &self.wilds
+            };
+            rank_sets(
+                stage,
+                &self.by_rank[stage],
+                &self.jokers,
+                wilds,
+                &self.options,
+                &mut seen.sets,
+                sink,
+            )
+        } else {
+            suit_runs(
+                &self.per_suit[stage - NUM_RANKS],
+                &self.jokers,
+                &self.wilds,
+                &self.options,
+                &mut seen.runs,
+                sink,
+            )
+        }
+    }
+}
+
+impl Iterator for MeldIterator {
+    type Item = Meld;
+
+    fn next(&mut self) -> Option<Meld> {
+        loop {
+            if let Some(meld) = self.pending.next() {
+                return Some(meld);
+            }
+            if self.stage >= NUM_RANKS + NUM_SUITS {
+                return None;
+            }
+            self.pending = self.generate(self.stage).into_iter();
+            self.stage += 1;
+        }
+    }
+}
+
+/// `enumerate_melds` for hand after hand under the same options, keeping the
+/// card buckets and seen-mask tables between calls and clearing them rather
+/// than allocating new ones. Every call takes `&mut self`, so a solver serves
+/// one thread at a time; give each thread its own.
+#[cfg_attr(feature = "python", pyclass)]
+pub struct Solver {
+    iter: MeldIterator,
+    seen: SeenMasks,
+}
+
+impl Solver {
+    pub fn new(options: &EnumOptions) -> Self {
+        Solver {
+            iter: MeldIterator::new(0, 0, options),
+            seen: SeenMasks::default(),
+        }
+    }
+
+    /// The melds `enumerate_melds` returns for this hand, in the same order.
+    pub fn enumerate(&mut self, mask_hi: u64, mask_lo: u64) -> Vec<Meld> {
+        self.iter.refill(mask_hi, mask_lo);
+        let mut sink = MeldSink::collecting_up_to(self.iter.options.meld_limit);
+        for stage in 0..NUM_RANKS + NUM_SUITS {
+            self.iter.generate_into(stage, &mut self.seen, &mut sink);
+        }
+        ordered(sink.into_melds(), &self.iter.options)
+    }
+
+    #[cfg(feature = "python")]
+    pub(crate) fn options(&self) -> &EnumOptions {
+        &self.iter.options
+    }
+}
+
+/// Kind of meld formed by exactly the cards in the mask, or `None` when they
+/// do not make one meld. Cards that read both ways (one natural card plus
+/// jokers) report `KIND_SET`, the kind the iterator yields first.
+pub fn classify_meld(mask_hi: u64, mask_lo: u64, options: &EnumOptions) -> Option<u8> {
+    MeldIterator::new(mask_hi, mask_lo, options)
+        .find(|meld| meld.mask_hi == mask_hi && meld.mask_lo == mask_lo)
+        .map(|meld| meld.kind)
+}
+
+/// Checks a meld with its jokers in place, as a game log records it. A run
+/// lists its cards from the lowest slot up, each printed joker standing for
+/// the rank of its slot; a set may list its cards in any order. As in
+/// `enumerate_melds`, wild-rank cards stand in like jokers except in a set of
+/// the wild rank itself, and both copies of a suit may share a set. The error
+/// names the rule the cards break.
+pub fn validate_meld_with_jokers(
+    card_ids: &[u8],
+    kind: u8,
+    options: &EnumOptions,
+) -> Result<(), String> {
+    let deck = &options.deck;
+    build_mask(card_ids, deck.card_count(), true)?;
+    // (slot, card_id, rank, suit) of each printed card, wild or not.
+    let mut wilds = Vec::new();
+    let mut naturals: Vec<(usize, u8, u8, u8)> = Vec::new();
+    for (slot, &card_id) in card_ids.iter().enumerate() {
+        let card = decode_card(card_id, deck);
+        let (Some(rank), Some(suit)) = (card.rank, card.suit) else {
+            continue;
+        };
+        if deck.is_wild(&card) {
+            wilds.push((slot, card_id, rank, suit));
+        } else {
+            naturals.push((slot, card_id, rank, suit));
+        }
+    }
+    if kind == KIND_SET && naturals.is_empty() {
+        naturals = wilds;
+    }
+    let Some(&(first_slot, first_id, first_rank, first_suit)) = naturals.first() else {
+        return Err("a meld needs at least one natural card".to_string());
+    };
+    let len = card_ids.len();
+    match kind {
+        KIND_SET => {
+            let (min, max) = (deck.min_set_size as usize, deck.max_set_size as usize);
+            if !(min..=max).contains(&len) {
+                return Err(format!("a set holds {min} to {max} cards, got {len}"));
+            }
+            for &(_, card_id, rank, _) in &naturals {
+                if rank != first_rank {
+                    return Err(format!(
+                        "{} does not share the rank of {}",
+                        card_name(card_id),
+                        card_name(first_id)
+                    ));
+                }
+            }
+            Ok(())
+        }
+        KIND_RUN => {
+            if len < 3 {
+                return Err(format!("a run needs at least 3 cards, got {len}"));
+            }
+            for &(slot, card_id, rank, suit) in &naturals {
+                if suit != first_suit {
+                    return Err(format!(
+                        "{} is not of the suit of {}",
+                        card_name(card_id),
+                        card_name(first_id)
+                    ));
+                }
+                let expected = (first_rank as usize + NUM_RANKS + slot - first_slot) % NUM_RANKS;
+                if rank as usize != expected {
+                    return Err(format!(
+                        "{} sits in slot {slot}, which needs a {} given {} in slot {first_slot}",
+                        card_name(card_id),
+                        RANK_TOKENS[expected],
+                        card_name(first_id)
+                    ));
+                }
+            }
+            // Every natural lines up; the run must also stay within the ranks
+            // the ace mode allows.
+            let fits = (0..NUM_RANKS).any(|start| {
+                (start + first_slot) % NUM_RANKS == first_rank as usize
+                    && start + len <= run_positions(options.ace_mode, start)
+            });
+            if !fits {
+                return Err(format!(
+                    "a run of {len} cards with {} in slot {first_slot} runs past the ace",
+                    card_name(first_id)
+                ));
+            }
+            Ok(())
+        }
+        _ => Err(format!("unknown meld kind {kind}")),
+    }
+}
+
+/// `card_to_string`, falling back to the ID for decks it cannot name.
+fn card_name(card_id: u8) -> String {
+    card_to_string(card_id).unwrap_or_else(|_| format!("card {card_id}"))
+}
+
+/// One hand card laid off onto a table meld:
+/// `(card_id, table_index, kind, freed_joker)`.
+pub type Layoff = (u8, usize, u8, Option<u8>);
+
+/// Ways to lay hand cards off onto melds already on the table, ordered by
+/// card, then table index. A card lays off when the table meld plus that
+/// card is still a meld of `kind`, as a run neighbour of its suit or another
+/// card of a set's rank.
+///
+/// With `swap_jokers`, a natural card may instead take the place of a printed
+/// joker in the table meld, named by `freed_joker`, as long as the cards left
+/// still form a meld.
+pub fn layoff_candidates(
+    hand_hi: u64,
+    hand_lo: u64,
+    table_melds: &[(u64, u64)],
+    swap_jokers: bool,
+    options: &EnumOptions,
+) -> Vec<Layoff> {
+    let mut candidates = Vec::new();
+    for card_id in mask_cards(merge_words(hand_hi, hand_lo)) {
+        let card_bit = card_bitmask(card_id);
+        for (table_index, &(meld_hi, meld_lo)) in table_melds.iter().enumerate() {
+            let meld_mask = merge_words(meld_hi, meld_lo);
+            if meld_mask & card_bit != 0 {
+                continue;
+            }
+            if let Some(kind) = classify_mask(meld_mask | card_bit, options) {
+                candidates.push((card_id, table_index, kind, None));
+            }
+            if swap_jokers {
+                for (_, joker_id, kind) in swaps_with_kind(card_bit, meld_mask, options) {
+                    candidates.push((card_id, table_index, kind, Some(joker_id)));
+                }
+            }
+        }
+    }
+    candidates
+}
+
+/// `(natural card, joker freed)` for each hand card that can reclaim a
+/// printed joker from the meld by taking its place, ordered by card, then
+/// joker. The card fits when it is one the joker could stand for: with it in
+/// the joker's slot the meld's cards still form a meld.
+pub fn joker_swaps(
+    hand_hi: u64,
+    hand_lo: u64,
+    meld_hi: u64,
+    meld_lo: u64,
+    options: &EnumOptions,
+) -> Vec<(u8, u8)> {
+    swaps_with_kind(
+        merge_words(hand_hi, hand_lo),
+        merge_words(meld_hi, meld_lo),
+        options,
+    )
+    .into_iter()
+    .map(|(card_id, joker_id, _)| (card_id, joker_id))
+    .collect()
+}
+
+/// `(card_id, joker_id, kind)` for each natural card of `hand_mask` that can
+/// take the place of a printed joker in `meld_mask`, leaving a meld of `kind`.
+fn swaps_with_kind(hand_mask: u128, meld_mask: u128, options: &EnumOptions) -> Vec<(u8, u8, u8)> {
+    let deck = &options.deck;
+    let jokers: Vec<u8> = mask_cards(meld_mask)
+        .into_iter()
+        .filter(|&card_id| card_id >= deck.first_joker_id())
+        .collect();
+    let mut swaps = Vec::new();
+    for card_id in mask_cards(hand_mask & !meld_mask) {
+        let card = decode_card(card_id, deck);
+        if card.rank.is_none() || deck.is_wild(&card) {
+            continue;
+        }
+        for &joker_id in &jokers {
+            let swapped = meld_mask & !card_bitmask(joker_id) | card_bitmask(card_id);
+            if let Some(kind) = classify_mask(swapped, options) {
+                swaps.push((card_id, joker_id, kind));
+            }
+        }
+    }
+    swaps
+}
+
+fn classify_mask(mask: u128, options: &EnumOptions) -> Option<u8> {
+    let (mask_hi, mask_lo) = combine_mask(mask);
+    classify_meld(mask_hi, mask_lo, options)
+}
+
+/// For each candidate card, the melds that drawing it would add to the hand:
+/// exactly the melds of the grown hand that contain it. Candidates already
+/// held gain nothing; every ID must lie inside the deck layout.
+///
+/// The hand is bucketed once and each draw only regenerates the set rank and
+/// run suit it touches, or every generator for a joker or wild card.
+pub fn melds_gained_by_draw(
+    mask_hi: u64,
+    mask_lo: u64,
+    candidates: &[u8],
+    options: &EnumOptions,
+) -> Vec<(u8, Vec<Meld>)> {
+    let hand = MeldIterator::new(mask_hi, mask_lo, options);
+    let hand_mask = merge_words(mask_hi, mask_lo);
+    candidates
+        .iter()
+        .map(|&card_id| {
+            let card_bit = card_bitmask(card_id);
+            if hand_mask & card_bit != 0 {
+                return (card_id, Vec::new());
+            }
+            let card = decode_card(card_id, &options.deck);
+            let stages: Vec<usize> = match (card.rank, card.suit) {
+                (Some(rank), Some(suit)) if !options.deck.is_wild(&card) => {
+                    vec![rank as usize, NUM_RANKS + suit as usize]
                 }
+                _ => (0..NUM_RANKS + NUM_SUITS).collect(),
+            };
+            let grown = hand.with_card(&card);
+            let mut gained: Vec<Meld> = stages
+                .into_iter()
+                .flat_map(|stage| grown.generate(stage))
+                .filter(|meld| merge_words(meld.mask_hi, meld.mask_lo) & card_bit != 0)
+                .collect();
+            gained.sort_by_key(Meld::sort_key);
+            (card_id, gained)
+        })
+        .collect()
+}
+
+/// Runs a few cards short: each window of at least three ranks of a suit
+/// whose end ranks are held and whose middle lacks between one and
+/// `max_missing` ranks. Each comes as the held cards of the window, the
+/// lowest copy of each rank, and the IDs of every copy that would fill a
+/// missing rank, suit by suit and then by window. Windows the hand's jokers
+/// could already fill are left out, and windows only span ranks a run could
+/// under the ace mode and `max_run_len`.
+pub fn near_runs(
+    mask_hi: u64,
+    mask_lo: u64,
+    max_missing: u8,
+    options: &EnumOptions,
+) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let deck = &options.deck;
+    let (by_rank, jokers) = collect_grouped(mask_hi, mask_lo, deck);
+    let max_len = match options.max_run_len {
+        0 => NUM_RANKS,
+        max_run_len => max_run_len.min(NUM_RANKS),
+    };
+    let mut near = Vec::new();
+    // The lowest copy of each card, suit by suit.
+    let held: [[Option<u8>; NUM_RANKS]; NUM_SUITS] = std::array::from_fn(|suit| {
+        std::array::from_fn(|rank| by_rank[rank][suit].first().copied())
+    });
+    for (suit, ranks) in held.iter().enumerate() {
+        for start in (0..NUM_RANKS).filter(|&start| ranks[start].is_some()) {
+            let end = run_positions(options.ace_mode, start).min(start + max_len);
+            let mut missing = Vec::new();
+            for last in start + 1..end {
+                let rank = last % NUM_RANKS;
+                if ranks[rank].is_none() {
+                    missing.push(rank);
+                    if missing.len() > max_missing as usize {
+                        break;
+                    }
+                    continue;
+                }
+                if last - start < 2 || missing.is_empty() || missing.len() <= jokers.len() {
+                    continue;
+                }
+                let have = (start..=last)
+                    .filter_map(|position| ranks[position % NUM_RANKS])
+                    .collect();
+                let needed = missing
+                    .iter()
+                    .flat_map(|&rank| {
+                        (0..deck.num_decks as usize).map(move |copy| {
+                            (copy * CARDS_PER_DECK + suit * NUM_RANKS + rank) as u8
+                        })
+                    })
+                    .collect();
+                near.push((have, needed));
             }
-            if seen_masks.insert(mask) {
-                let (mask_hi, mask_lo) = combine_mask(mask);
-                results.push(Meld {
+        }
+    }
+    near
+}
+
+/// The `enumerate_melds` output for the hand, given `prev_melds`, that output
+/// for the hand differing from this one in `changed_id` alone. Only the set
+/// rank and run suit of the card are regenerated, or every generator for a
+/// joker or wild card; the other melds are reused as they are.
+pub fn melds_after_change(
+    prev_melds: &[Meld],
+    mask_hi: u64,
+    mask_lo: u64,
+    changed_id: u8,
+    options: &EnumOptions,
+) -> Vec<Meld> {
+    let card = decode_card(changed_id, &options.deck);
+    let (Some(rank), Some(suit)) = (card.rank, card.suit) else {
+        return enumerate_melds(mask_hi, mask_lo, options);
+    };
+    if options.deck.is_wild(&card) {
+        return enumerate_melds(mask_hi, mask_lo, options);
+    }
+    let stages = [rank as usize, NUM_RANKS + suit as usize];
+    let hand_mask = merge_words(mask_hi, mask_lo);
+    let iter = MeldIterator::new(mask_hi, mask_lo, options);
+    let melds = prev_melds
+        .iter()
+        .filter(|meld| {
+            merge_words(meld.mask_hi, meld.mask_lo) & !hand_mask == 0
+                && !stages.contains(&meld_stage(meld, &options.deck))
+        })
+        .cloned()
+        .chain(stages.iter().flat_map(|&stage| iter.generate(stage)))
+        .collect();
+    ordered(melds, options)
+}
+
+/// The `MeldIterator` stage that generates `meld`: its rank for a set, its
+/// suit after the ranks for a run. Wild cards only pin a set of their rank.
+fn meld_stage(meld: &Meld, deck: &DeckConfig) -> usize {
+    let natural = mask_cards(merge_words(meld.mask_hi, meld.mask_lo))
+        .into_iter()
+        .map(|card_id| decode_card(card_id, deck))
+        .find_map(|card| match (card.rank, card.suit) {
+            (Some(rank), Some(suit)) if !deck.is_wild(&card) => Some((rank, suit)),
+            _ => None,
+        });
+    match (meld.kind, natural) {
+        (KIND_RUN, Some((_, suit))) => NUM_RANKS + suit as usize,
+        (_, Some((rank, _))) => rank as usize,
+        // Only a set of the wild rank holds nothing but wild cards and jokers.
+        (_, None) => deck.wild_rank.map_or(0, usize::from),
+    }
+}
+
+/// The `enumerate_melds` melds holding `card_id`, in the same order; none
+/// when the hand lacks the card. A natural card can only sit in a set of
+/// its rank or a run of its suit, so only those two generators run for it,
+/// while a joker or wild card goes through the whole enumeration. Like
+/// `melds_after_change`, the staged path ignores `EnumOptions::meld_limit`.
+/// Partial melds are never listed.
+pub fn melds_containing(
+    mask_hi: u64,
+    mask_lo: u64,
+    card_id: u8,
+    options: &EnumOptions,
+) -> Vec<Meld> {
+    let hand_mask = merge_words(mask_hi, mask_lo);
+    if card_id as u32 >= u128::BITS || hand_mask & card_bitmask(card_id) == 0 {
+        return Vec::new();
+    }
+    let card = decode_card(card_id, &options.deck);
+    let melds = match (card.rank, card.suit) {
+        (Some(rank), Some(suit)) if !options.deck.is_wild(&card) => {
+            let iter = MeldIterator::new(mask_hi, mask_lo, options);
+            [rank as usize, NUM_RANKS + suit as usize]
+                .into_iter()
+                .flat_map(|stage| iter.generate(stage))
+                .collect()
+        }
+        _ => enumerate_melds(mask_hi, mask_lo, options),
+    };
+    let containing = melds
+        .into_iter()
+        .filter(|meld| {
+            meld.kind != KIND_PARTIAL
+                && merge_words(meld.mask_hi, meld.mask_lo) & card_bitmask(card_id) != 0
+        })
+        .collect();
+    ordered(containing, options)
+}
+
+/// For each card in the hand, ascending, the indices into the
+/// `enumerate_melds` output of the melds that contain it.
+pub fn melds_by_card(mask_hi: u64, mask_lo: u64, options: &EnumOptions) -> Vec<(u8, Vec<usize>)> {
+    let hand_cards = mask_cards(merge_words(mask_hi, mask_lo));
+    let mut slots = [usize::MAX; 128];
+    for (slot, &card_id) in hand_cards.iter().enumerate() {
+        slots[card_id as usize] = slot;
+    }
+    let mut buckets: Vec<(u8, Vec<usize>)> = hand_cards
+        .into_iter()
+        .map(|card_id| (card_id, Vec::new()))
+        .collect();
+    for (idx, meld) in enumerate_melds(mask_hi, mask_lo, options)
+        .iter()
+        .enumerate()
+    {
+        for card_id in mask_cards(merge_words(meld.mask_hi, meld.mask_lo)) {
+            buckets[slots[card_id as usize]].1.push(idx);
+        }
+    }
+    buckets
+}
+
+/// Number of melds `enumerate_melds` would return, found without building
+/// any of them.
+pub fn count_melds(mask_hi: u64, mask_lo: u64, options: &EnumOptions) -> usize {
+    let iter = MeldIterator::new(mask_hi, mask_lo, options);
+    let mut sink = MeldSink::Count(0);
+    let mut seen = SeenMasks::default();
+    for stage in 0..NUM_RANKS + NUM_SUITS {
+        iter.generate_into(stage, &mut seen, &mut sink);
+    }
+    match sink {
+        MeldSink::Count(count) => count,
+        MeldSink::Collect { .. } => unreachable!("counting sink"),
+    }
+}
+
+pub fn enumerate_melds(mask_hi: u64, mask_lo: u64, options: &EnumOptions) -> Vec<Meld> {
+    enumerate_melds_capped(mask_hi, mask_lo, options).0
+}
+
+/// `enumerate_melds` along with whether `EnumOptions::meld_limit` cut it
+/// short.
+pub fn enumerate_melds_capped(
+    mask_hi: u64,
+    mask_lo: u64,
+    options: &EnumOptions,
+) -> (Vec<Meld>, bool) {
+    let iter = MeldIterator::new(mask_hi, mask_lo, options);
+    let mut sink = MeldSink::collecting_up_to(options.meld_limit);
+    let mut seen = SeenMasks::default();
+    for stage in 0..NUM_RANKS + NUM_SUITS {
+        iter.generate_into(stage, &mut seen, &mut sink);
+    }
+    if options.include_partials {
+        partial_melds_into(merge_words(mask_hi, mask_lo), options, &mut sink);
+    }
+    let truncated = sink.truncated();
+    (ordered(sink.into_melds(), options), truncated)
+}
+
+/// Pairs of natural cards one card short of a meld, as `KIND_PARTIAL`
+/// melds: two suits of a rank, or two ranks of a suit close enough for one
+/// card to join them into a run under the ace mode. A pair of a rank needs
+/// as many more cards as `DeckConfig::min_set_size` asks for, so none are
+/// listed when two cards already make a set. Jokers and wild cards are left
+/// out.
+fn partial_melds_into(mask: u128, options: &EnumOptions, sink: &mut MeldSink) {
+    let deck = &options.deck;
+    let scoring = &options.scoring;
+    let cards: Vec<(u8, u8, u8)> = mask_cards(mask)
+        .into_iter()
+        .map(|card_id| decode_card(card_id, deck))
+        .filter(|card| !deck.is_wild(card))
+        .filter_map(|card| Some((card.id, card.rank?, card.suit?)))
+        .collect();
+    let set_needed = deck.min_set_size.saturating_sub(2);
+    for (idx, &(card_id, rank, suit)) in cards.iter().enumerate() {
+        for &(other_id, other_rank, other_suit) in &cards[idx + 1..] {
+            let (points, cards_needed) = if rank == other_rank {
+                if suit == other_suit || set_needed == 0 {
+                    continue;
+                }
+                (
+                    (scoring.set_points(rank), scoring.set_points(rank)),
+                    set_needed,
+                )
+            } else if suit == other_suit && run_neighbours(rank, other_rank, options.ace_mode) {
+                // A pair further apart connects through the king, past which
+                // the lower rank sits.
+                let lift = |rank: u8, other_rank: u8| {
+                    if rank < other_rank && other_rank - rank > 2 {
+                        rank as usize + NUM_RANKS
+                    } else {
+                        rank as usize
+                    }
+                };
+                let (position, other_position) = (lift(rank, other_rank), lift(other_rank, rank));
+                (
+                    (
+                        scoring.run_points(position),
+                        scoring.run_points(other_position),
+                    ),
+                    1,
+                )
+            } else {
+                continue;
+            };
+            sink.push(|| {
+                let (mask_hi, mask_lo) =
+                    combine_mask(card_bitmask(card_id) | card_bitmask(other_id));
+                Meld {
                     mask_hi,
                     mask_lo,
-                    points,
+                    points: meld_points(points.0 as i64 + points.1 as i64),
                     jokers_used: 0,
-                    kind: KIND_RUN,
-                });
-            }
+                    wilds_used: 0,
+                    kind: KIND_PARTIAL,
+                    is_pure: true,
+                    points_by_card: vec![(card_id, points.0), (other_id, points.1)],
+                    joker_ids: Vec::new(),
+                    cards_needed,
+                }
+            });
         }
-        if current_rank + 1 < NUM_RANKS && !rank_lists[current_rank + 1].is_empty() {
-            explore_run(
-                rank_lists,
-                current_rank + 1,
-                current_cards,
-                seen_masks,
-                results,
-            );
+    }
+}
+
+/// Whether two distinct ranks of one suit sit within two ranks of each other
+/// in some run `ace_mode` allows.
+pub(crate) fn run_neighbours(rank: u8, other_rank: u8, ace_mode: u8) -> bool {
+    let (low, high) = (rank.min(other_rank), rank.max(other_rank));
+    if (1..=2).contains(&(high - low)) {
+        return true;
+    }
+    // Going up from `high` through the king and round to `low`.
+    let through_king = low as usize + NUM_RANKS - high as usize <= 2;
+    match ace_mode {
+        ACE_WRAP => through_king,
+        ACE_HIGH_LOW => through_king && low == 0,
+        _ => false,
+    }
+}
+
+/// `enumerate_melds` for a hand given as card IDs. Each ID names one
+/// physical card, so an ID outside the layout or listed twice is an error.
+pub fn enumerate_melds_from_ids(
+    card_ids: &[u8],
+    options: &EnumOptions,
+) -> Result<Vec<Meld>, String> {
+    let mask = build_mask(card_ids, options.deck.card_count(), true)?;
+    let (mask_hi, mask_lo) = combine_mask(mask);
+    Ok(enumerate_melds(mask_hi, mask_lo, options))
+}
+
+/// `enumerate_melds` along with counters describing the enumeration.
+pub fn enumerate_melds_stats(
+    mask_hi: u64,
+    mask_lo: u64,
+    options: &EnumOptions,
+) -> (Vec<Meld>, EnumStats) {
+    let iter = MeldIterator::new(mask_hi, mask_lo, options);
+    let mut sink = MeldSink::collecting_up_to(options.meld_limit);
+    let mut seen = SeenMasks::default();
+    for stage in 0..NUM_RANKS + NUM_SUITS {
+        iter.generate_into(stage, &mut seen, &mut sink);
+    }
+    let MeldSink::Collect {
+        melds,
+        duplicates,
+        truncated,
+        ..
+    } = sink
+    else {
+        unreachable!("collecting sink")
+    };
+    let card_count = |meld: &Meld| (meld.mask_hi.count_ones() + meld.mask_lo.count_ones()) as u8;
+    let runs: Vec<&Meld> = melds.iter().filter(|meld| meld.kind == KIND_RUN).collect();
+    let stats = EnumStats {
+        sets: melds.len() - runs.len(),
+        runs: runs.len(),
+        duplicates,
+        longest_run: runs.iter().map(|meld| card_count(meld)).max().unwrap_or(0),
+        truncated,
+    };
+    (ordered(melds, options), stats)
+}
+
+/// The runs `enumerate_melds` would return, in the same order.
+pub fn enumerate_runs(mask_hi: u64, mask_lo: u64, options: &EnumOptions) -> Vec<Meld> {
+    let iter = MeldIterator::new(mask_hi, mask_lo, options);
+    let melds = (NUM_RANKS..NUM_RANKS + NUM_SUITS)
+        .flat_map(|stage| iter.generate(stage))
+        .collect();
+    ordered(melds, options)
+}
+
+/// The sets `enumerate_melds` would return, in the same order.
+pub fn enumerate_sets(mask_hi: u64, mask_lo: u64, options: &EnumOptions) -> Vec<Meld> {
+    let iter = MeldIterator::new(mask_hi, mask_lo, options);
+    let melds = (0..NUM_RANKS)
+        .flat_map(|stage| iter.generate(stage))
+        .collect();
+    ordered(melds, options)
+}
+
+/// Sorts melds into the canonical order, then by `options.order`.
+fn ordered(mut melds: Vec<Meld>, options: &EnumOptions) -> Vec<Meld> {
+    melds.sort_by_key(Meld::sort_key);
+    match options.order {
+        ORDER_POINTS => melds.sort_by_key(|meld| (Reverse(meld.points), meld.jokers_used)),
+        ORDER_CARDS => {
+            melds.sort_by_key(|meld| Reverse(meld.mask_hi.count_ones() + meld.mask_lo.count_ones()))
         }
-        current_cards.pop();
+        _ => {}
     }
+    melds
+}
+
+/// `enumerate_melds` as `(kind, card IDs)` pairs, for Rust callers that work
+/// with card lists rather than masks. Card IDs are ascending.
+pub fn enumerate_meld_cards(
+    mask_hi: u64,
+    mask_lo: u64,
+    options: &EnumOptions,
+) -> Vec<(u8, Vec<u8>)> {
+    enumerate_melds(mask_hi, mask_lo, options)
+        .into_iter()
+        .map(|meld| {
+            (
+                meld.kind,
+                mask_cards(merge_words(meld.mask_hi, meld.mask_lo)),
+            )
+        })
+        .collect()
 }
 
-fn enumerate_runs(cards: &[CardInfo]) -> Vec<Meld> {
-    let mut per_suit = vec![vec![Vec::<u8>::new(); NUM_RANKS]; NUM_SUITS];
-    for card in cards {
-        if let (Some(rank), Some(suit)) = (card.rank, card.suit) {
-            per_suit[suit as usize][rank as usize].push(card.id);
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    /// What a run reading decides: its cards, points and per-card points.
+    type RunReading = (u64, u64, i32, Vec<(u8, i32)>);
+    type RunGenerator = fn(&[Vec<u8>], &EnumOptions, &mut HashMap<u128, usize>, &mut MeldSink);
+
+    fn runs(
+        rank_lists: &[Vec<u8>],
+        options: &EnumOptions,
+        generate: RunGenerator,
+    ) -> Vec<RunReading> {
+        let mut sink = MeldSink::collecting();
+        generate(rank_lists, options, &mut HashMap::new(), &mut sink);
+        sink.into_melds()
+            .into_iter()
+            .map(|meld| (meld.mask_hi, meld.mask_lo, meld.points, meld.points_by_card))
+            .collect()
+    }
+
+    #[test]
+    fn single_copy_runs_match_the_walk() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for trial in 0..200 {
+            // Spades of the first deck hold IDs 0..13, one per rank; the first
+            // trial holds the whole suit, whose runs repeat once they wrap.
+            let keep = if trial == 0 { 1.0 } else { 0.7 };
+            let rank_lists: Vec<Vec<u8>> = (0..NUM_RANKS as u8)
+                .map(|rank| {
+                    if rng.gen_bool(keep) {
+                        vec![rank]
+                    } else {
+                        Vec::new()
+                    }
+                })
+                .collect();
+            for ace_mode in [ACE_LOW, ACE_HIGH_LOW, ACE_WRAP] {
+                for (only_maximal_runs, max_run_len) in
+                    [(false, 0), (true, 0), (false, 4), (true, 5)]
+                {
+                    let options = EnumOptions {
+                        ace_mode,
+                        only_maximal_runs,
+                        max_run_len,
+                        ..Default::default()
+                    };
+                    let walked = runs(&rank_lists, &options, |lists, options, seen_masks, sink| {
+                        walked_runs(lists, &[], &[], options, seen_masks, sink)
+                    });
+                    assert_eq!(
+                        runs(&rank_lists, &options, single_copy_runs),
+                        walked,
+                        "ranks {rank_lists:?}, ace mode {ace_mode}"
+                    );
+                }
+            }
         }
     }
 
-    let mut results = Vec::new();
-    let mut seen_masks: HashSet<u128> = HashSet::new();
-    for suit in 0..NUM_SUITS {
-        let rank_lists = &per_suit[suit];
-        for start in 0..NUM_RANKS {
-            if rank_lists[start].is_empty() {
-                continue;
+    /// Times both run paths over the same dense suits. Run it with
+    /// `cargo test --release -- --ignored --nocapture single_copy_runs`.
+    #[test]
+    #[ignore = "timing comparison, run by hand in release builds"]
+    fn time_single_copy_runs_against_the_walk() {
+        const ITERATIONS: u32 = 100_000;
+        let walk: RunGenerator = |lists, options, seen_masks, sink| {
+            walked_runs(lists, &[], &[], options, seen_masks, sink)
+        };
+        // A ten-card suit missing 4, 8 and J, and the whole suit.
+        let suits: [&[u8]; 2] = [
+            &[0, 1, 2, 4, 5, 6, 8, 9, 11, 12],
+            &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12],
+        ];
+        for ranks in suits {
+            let rank_lists: Vec<Vec<u8>> = (0..NUM_RANKS as u8)
+                .map(|rank| ranks.iter().copied().filter(|&held| held == rank).collect())
+                .collect();
+            for ace_mode in [ACE_LOW, ACE_HIGH_LOW, ACE_WRAP] {
+                let options = EnumOptions {
+                    ace_mode,
+                    ..Default::default()
+                };
+                assert_eq!(
+                    runs(&rank_lists, &options, single_copy_runs),
+                    runs(&rank_lists, &options, walk)
+                );
+                let time = |generate: RunGenerator| {
+                    let mut seen_masks = HashMap::new();
+                    let started = Instant::now();
+                    for _ in 0..ITERATIONS {
+                        let mut sink = MeldSink::collecting();
+                        generate(&rank_lists, &options, &mut seen_masks, &mut sink);
+                        std::hint::black_box(sink.into_melds());
+                    }
+                    started.elapsed()
+                };
+                println!(
+                    "{} cards, ace mode {ace_mode}: bitmask {:?}, walk {:?} over {ITERATIONS} runs",
+                    ranks.len(),
+                    time(single_copy_runs),
+                    time(walk),
+                );
             }
-            let mut current_cards = Vec::new();
-            explore_run(
-                rank_lists,
-                start,
-                &mut current_cards,
+        }
+    }
+
+    #[test]
+    fn rank_sets_match_every_subset_of_the_rank() {
+        let mut rng = StdRng::seed_from_u64(90);
+        for _ in 0..200 {
+            // Sevens of both decks, at most two of each suit, plus jokers and
+            // deuces standing in as wild cards.
+            let suit_lists: Vec<Vec<u8>> = (0..NUM_SUITS)
+                .map(|suit| {
+                    (0..2)
+                        .filter(|_| rng.gen_bool(0.6))
+                        .map(|copy| (copy * CARDS_PER_DECK + suit * NUM_RANKS + 6) as u8)
+                        .collect()
+                })
+                .collect();
+            let jokers: Vec<u8> = [104, 105]
+                .into_iter()
+                .filter(|_| rng.gen_bool(0.5))
+                .collect();
+            let wilds: Vec<u8> = [1, 14].into_iter().filter(|_| rng.gen_bool(0.3)).collect();
+            let mut options = EnumOptions::default();
+            options.deck.min_set_size = rng.gen_range(2..=3);
+            options.deck.max_set_size = rng.gen_range(3..=5);
+            options.max_jokers_per_meld = rng.gen_range(0..=2);
+
+            let mut sink = MeldSink::collecting();
+            let mut seen_masks = HashSet::new();
+            let suits = &suit_lists;
+            rank_sets(
+                6,
+                suits,
+                &jokers,
+                &wilds,
+                &options,
                 &mut seen_masks,
-                &mut results,
+                &mut sink,
             );
+            let mut generated: Vec<(u128, u8, u8)> = sink
+                .into_melds()
+                .into_iter()
+                .map(|meld| {
+                    let mask = merge_words(meld.mask_hi, meld.mask_lo);
+                    (mask, meld.jokers_used, meld.wilds_used)
+                })
+                .collect();
+            generated.sort_unstable();
+
+            let naturals: Vec<u8> = suit_lists.iter().flatten().copied().collect();
+            let cards: Vec<u8> = naturals
+                .iter()
+                .chain(&jokers)
+                .chain(&wilds)
+                .copied()
+                .collect();
+            let count_in = |subset: &[u8], of: &[u8]| -> u8 {
+                subset.iter().filter(|id| of.contains(id)).count() as u8
+            };
+            let mut expected: Vec<(u128, u8, u8)> = (0u32..1 << cards.len())
+                .map(|bits| {
+                    (0..cards.len())
+                        .filter(|&idx| bits & 1 << idx != 0)
+                        .map(|idx| cards[idx])
+                        .collect::<Vec<u8>>()
+                })
+                .filter(|subset| {
+                    (options.deck.min_set_size as usize..=options.deck.max_set_size as usize)
+                        .contains(&subset.len())
+                        && count_in(subset, &naturals) > 0
+                        && count_in(subset, &jokers) <= options.max_jokers_per_meld
+                })
+                .map(|subset| {
+                    let mask = subset
+                        .iter()
+                        .fold(0u128, |mask, &card_id| mask | card_bitmask(card_id));
+                    (mask, count_in(&subset, &jokers), count_in(&subset, &wilds))
+                })
+                .collect();
+            expected.sort_unstable();
+            assert_eq!(generated, expected, "{suit_lists:?} {jokers:?} {wilds:?}");
         }
     }
-    results
-}
 
-pub fn enumerate_melds(mask_hi: u64, mask_lo: u64) -> Vec<Meld> {
-    let (cards, jokers) = collect_cards(mask_hi, mask_lo);
-    let mut melds = enumerate_sets(&cards, &jokers);
-    melds.extend(enumerate_runs(&cards));
-    melds.sort_by(|a, b| {
-        (a.mask_hi, a.mask_lo, a.kind, a.jokers_used, a.points).cmp(&(
-            b.mask_hi,
-            b.mask_lo,
-            b.kind,
-            b.jokers_used,
-            b.points,
-        ))
-    });
-    melds
+    #[test]
+    fn runs_mix_the_copies_of_both_decks() {
+        let mut rng = StdRng::seed_from_u64(99);
+        let options = EnumOptions::default();
+        for _ in 0..100 {
+            // Spades of either deck: ID `rank` or `rank + 52`.
+            let hand: Vec<u8> = (0..NUM_RANKS as u8)
+                .flat_map(|rank| [rank, rank + CARDS_PER_DECK as u8])
+                .filter(|_| rng.gen_bool(0.5))
+                .collect();
+            let hand_mask = hand.iter().fold(0u128, |mask, &id| mask | card_bitmask(id));
+            let (mask_hi, mask_lo) = combine_mask(hand_mask);
+            let mut runs: Vec<u128> = enumerate_runs(mask_hi, mask_lo, &options)
+                .iter()
+                .map(|meld| merge_words(meld.mask_hi, meld.mask_lo))
+                .collect();
+            runs.sort_unstable();
+
+            // Every choice of one held copy per rank over each span of ranks.
+            let mut expected = Vec::new();
+            for start in 0..NUM_RANKS {
+                let mut partial = vec![0u128];
+                for rank in start..NUM_RANKS {
+                    let copies: Vec<u8> = [rank, rank + CARDS_PER_DECK]
+                        .into_iter()
+                        .map(|id| id as u8)
+                        .filter(|id| hand.contains(id))
+                        .collect();
+                    partial = partial
+                        .iter()
+                        .flat_map(|&mask| copies.iter().map(move |&id| mask | card_bitmask(id)))
+                        .collect();
+                    if rank - start >= 2 {
+                        expected.extend(&partial);
+                    }
+                }
+            }
+            expected.sort_unstable();
+            assert_eq!(runs, expected, "{hand:?}");
+        }
+    }
+
+    #[test]
+    fn enumerated_melds_pass_validation() {
+        let mut rng = StdRng::seed_from_u64(68);
+        for _ in 0..200 {
+            // Both decks and their jokers, with or without deuces wild.
+            let mut options = EnumOptions {
+                ace_mode: [ACE_LOW, ACE_HIGH_LOW, ACE_WRAP][rng.gen_range(0..3)],
+                ..Default::default()
+            };
+            options.deck.wild_rank = rng.gen_bool(0.5).then_some(1);
+            let deck = options.deck;
+            let hand = (0..deck.card_count() as u8)
+                .filter(|_| rng.gen_bool(0.12))
+                .fold(0u128, |mask, id| mask | card_bitmask(id));
+            let (mask_hi, mask_lo) = combine_mask(hand);
+            let stands_in = |card_id: u8| {
+                let card = decode_card(card_id, &deck);
+                card.rank.is_none() || deck.is_wild(&card)
+            };
+            for meld in enumerate_melds(mask_hi, mask_lo, &options) {
+                let cards = mask_cards(merge_words(meld.mask_hi, meld.mask_lo));
+                if meld.kind == KIND_SET {
+                    if let Err(err) = validate_meld_with_jokers(&cards, KIND_SET, &options) {
+                        panic!("set {cards:?}, wild rank {:?}: {err}", deck.wild_rank);
+                    }
+                    continue;
+                }
+                // Lay the run out from each starting rank in turn, naturals in
+                // the slot of their rank and the substitutes in the gaps.
+                let (fillers, naturals): (Vec<u8>, Vec<u8>) =
+                    cards.iter().partition(|&&card_id| stands_in(card_id));
+                let laid_out = (0..NUM_RANKS).find_map(|start| {
+                    let mut slots = vec![None; cards.len()];
+                    for &card_id in &naturals {
+                        let rank = decode_card(card_id, &deck).rank? as usize;
+                        let slot = (rank + NUM_RANKS - start) % NUM_RANKS;
+                        if slots.get(slot)?.is_some() {
+                            return None;
+                        }
+                        slots[slot] = Some(card_id);
+                    }
+                    let mut fillers = fillers.iter().copied();
+                    let order: Vec<u8> = slots
+                        .iter()
+                        .map(|slot| slot.or_else(|| fillers.next()))
+                        .collect::<Option<_>>()?;
+                    validate_meld_with_jokers(&order, KIND_RUN, &options).ok()
+                });
+                assert!(
+                    laid_out.is_some(),
+                    "run {cards:?}, ace mode {}, wild rank {:?}",
+                    options.ace_mode,
+                    deck.wild_rank
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn meld_cards_list_each_enumerated_meld() {
+        let hand = crate::parse_hand(&["5S", "6S", "6S#2", "7S", "7H", "7D", "J1", "J2"]).unwrap();
+        let (mask_hi, mask_lo) = combine_mask(hand);
+        let options = EnumOptions::default();
+        let expected: Vec<(u8, Vec<u8>)> = enumerate_melds(mask_hi, mask_lo, &options)
+            .iter()
+            .map(|meld| {
+                let cards = mask_cards(merge_words(meld.mask_hi, meld.mask_lo));
+                (meld.kind, cards)
+            })
+            .collect();
+        assert!(expected.iter().any(|(kind, _)| *kind == KIND_SET));
+        assert!(expected.iter().any(|(kind, _)| *kind == KIND_RUN));
+        // 6S#2 and the second joker are IDs 57 and 105.
+        assert!(expected.iter().any(|(_, cards)| cards.contains(&57)));
+        assert!(expected.iter().any(|(_, cards)| cards.contains(&105)));
+        assert_eq!(enumerate_meld_cards(mask_hi, mask_lo, &options), expected);
+    }
+
+    #[test]
+    fn aces_score_apart_in_sets_and_runs() {
+        let hand = crate::parse_hand(&["AS", "2S", "3S", "AH", "AD"]).unwrap();
+        let (mask_hi, mask_lo) = combine_mask(hand);
+        let options = EnumOptions {
+            scoring: Scoring {
+                ace_in_set: Some(11),
+                ace_low_in_run: Some(1),
+                ..Scoring::STANDARD
+            },
+            ..Default::default()
+        };
+        let runs = enumerate_runs(mask_hi, mask_lo, &options);
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].points, 1 + 2 + 3);
+        let sets = enumerate_sets(mask_hi, mask_lo, &options);
+        assert_eq!(sets.len(), 1);
+        assert_eq!(sets[0].points, 33);
+        assert!(sets[0]
+            .points_by_card
+            .iter()
+            .all(|&(_, points)| points == 11));
+    }
+
+    #[test]
+    fn aces_score_apart_at_either_end_of_a_run() {
+        let scoring = Scoring {
+            ace_low_in_run: Some(1),
+            ace_high_in_run: Some(11),
+            ..Scoring::STANDARD
+        };
+        let points_of = |cards: &[&str], ace_mode: u8| {
+            let hand = crate::parse_hand(cards).unwrap();
+            let (mask_hi, mask_lo) = combine_mask(hand);
+            let options = EnumOptions {
+                scoring,
+                ace_mode,
+                ..Default::default()
+            };
+            let runs = enumerate_runs(mask_hi, mask_lo, &options);
+            let run = runs
+                .iter()
+                .find(|run| merge_words(run.mask_hi, run.mask_lo) == hand)
+                .unwrap();
+            let ace = crate::parse_card(cards[0]).unwrap();
+            let ace_points = run.points_by_card.iter().find(|&&(id, _)| id == ace);
+            (run.points, ace_points.unwrap().1)
+        };
+        assert_eq!(points_of(&["AS", "2S", "3S"], ACE_HIGH_LOW), (1 + 2 + 3, 1));
+        assert_eq!(
+            points_of(&["AH", "QH", "KH"], ACE_HIGH_LOW),
+            (11 + 10 + 10, 11)
+        );
+        assert_eq!(points_of(&["AD", "KD", "2D"], ACE_WRAP), (11 + 10 + 2, 11));
+        // Left unset, both ends keep the ace's table value.
+        let standard = EnumOptions {
+            ace_mode: ACE_HIGH_LOW,
+            ..Default::default()
+        };
+        let hand = crate::parse_hand(&["AC", "2C", "3C", "QC", "KC"]).unwrap();
+        let (mask_hi, mask_lo) = combine_mask(hand);
+        let mut points: Vec<i32> = enumerate_runs(mask_hi, mask_lo, &standard)
+            .iter()
+            .map(|run| run.points)
+            .collect();
+        points.sort_unstable();
+        assert_eq!(points, [10 + 2 + 3, 10 + 10 + 10]);
+    }
+
+    #[test]
+    fn jokers_score_as_their_slot_only_when_asked() {
+        let hand = crate::parse_hand(&["5S", "6S", "KS", "KH", "J1"]).unwrap();
+        let (mask_hi, mask_lo) = combine_mask(hand);
+        let joker = crate::parse_card("J1").unwrap();
+        for (joker_scores_as_slot, joker_value) in [(true, 1), (false, 0)] {
+            let options = EnumOptions {
+                scoring: Scoring {
+                    joker_scores_as_slot,
+                    ..Scoring::STANDARD
+                },
+                ..Default::default()
+            };
+            let sets = enumerate_sets(mask_hi, mask_lo, &options);
+            assert_eq!(sets.len(), 1);
+            assert_eq!(sets[0].points, 20 + 10 * joker_value);
+            // 5-6 with the joker reads best as 5-6-7, worth 7 when it scores.
+            let runs = enumerate_runs(mask_hi, mask_lo, &options);
+            assert_eq!(runs.len(), 1);
+            assert_eq!(runs[0].points, 11 + 7 * joker_value);
+            for meld in sets.iter().chain(&runs) {
+                let total: i32 = meld.points_by_card.iter().map(|&(_, points)| points).sum();
+                assert_eq!(total, meld.points);
+                let (_, joker_points) = meld
+                    .points_by_card
+                    .iter()
+                    .find(|&&(id, _)| id == joker)
+                    .unwrap();
+                assert_eq!(*joker_points != 0, joker_scores_as_slot);
+            }
+        }
+    }
+
+    #[test]
+    fn joker_bonus_tells_the_jokers_apart() {
+        let hand = crate::parse_hand(&["5S", "6S", "KS", "KH", "J1", "J2"]).unwrap();
+        let (mask_hi, mask_lo) = combine_mask(hand);
+        let mut options = EnumOptions {
+            scoring: Scoring {
+                joker_bonus: [0, 5],
+                ..Scoring::STANDARD
+            },
+            ..Default::default()
+        };
+        // Points of the melds made of exactly `naturals` and `joker_ids`.
+        let points_of = |melds: &[Meld], naturals: &[&str], joker_ids: &[u8]| -> Vec<i32> {
+            let naturals = crate::parse_hand(naturals).unwrap();
+            let jokers = joker_ids
+                .iter()
+                .fold(0u128, |mask, &id| mask | card_bitmask(id));
+            melds
+                .iter()
+                .filter(|meld| merge_words(meld.mask_hi, meld.mask_lo) == naturals | jokers)
+                .map(|meld| meld.points)
+                .collect()
+        };
+        let sets = enumerate_sets(mask_hi, mask_lo, &options);
+        assert_eq!(points_of(&sets, &["KS", "KH"], &[104]), [30]);
+        assert_eq!(points_of(&sets, &["KS", "KH"], &[105]), [35]);
+        assert_eq!(points_of(&sets, &["KS", "KH"], &[104, 105]), [45]);
+        let runs = enumerate_runs(mask_hi, mask_lo, &options);
+        assert_eq!(points_of(&runs, &["5S", "6S"], &[104]), [18]);
+        assert_eq!(points_of(&runs, &["5S", "6S"], &[105]), [23]);
+
+        // Collapsed, only the lowest joker IDs remain, bonus or not.
+        options.dedup_joker_assignments = true;
+        let sets = enumerate_sets(mask_hi, mask_lo, &options);
+        assert_eq!(points_of(&sets, &["KS", "KH"], &[104]), [30]);
+        assert!(points_of(&sets, &["KS", "KH"], &[105]).is_empty());
+    }
 }
@@ -6,7 +6,18 @@ import pytest
 
 from konkan import encoding
 from konkan.melds import (
+    ACE_HIGH_LOW,
+    ACE_LOW,
+    ACE_WRAP,
     HAVE_NATIVE_SOLVER,
+    OBJ_FIRST_14,
+    OBJ_MAX_CARDS,
+    OBJ_MAX_CARDS_SAFE,
+    OBJ_MAX_POINTS,
+    OBJ_MIN_DEADWOOD,
+    OBJ_MIN_DEADWOOD_SOFT,
+    OBJ_MIN_JOKERS,
+    best_cover,
     best_cover_for_go_out,
     best_cover_to_threshold,
     enumerate_melds,
@@ -33,6 +44,59 @@ def _mask_from_cards(cards: Iterable[int]) -> tuple[int, int]:
     return encoding.split_mask(mask)
 
 
+def _score_key(
+    objective: int, threshold: int, total: int, covered: int, points: int, jokers: int
+) -> tuple[int, ...]:
+    deadwood = total - covered
+    if objective == OBJ_MIN_DEADWOOD:
+        return (points >= threshold, -deadwood, points, covered, -jokers)
+    if objective == OBJ_FIRST_14:
+        return (covered >= 14, -deadwood, covered, points, -jokers)
+    if objective == OBJ_MAX_POINTS:
+        return (points, covered, -jokers)
+    if objective == OBJ_MIN_JOKERS:
+        return (-jokers, covered, points)
+    return (covered, points, -deadwood, -jokers)
+
+
+def _exhaustive_best_key(
+    mask_hi: int, mask_lo: int, objective: int, threshold: int
+) -> tuple[int, ...]:
+    """Score of the best disjoint meld selection, found without any pruning."""
+
+    melds = [
+        (encoding.combine_mask(m.mask_hi, m.mask_lo), m.points, m.jokers_used)
+        for m in enumerate_melds(mask_hi, mask_lo)
+    ]
+    total = bin(encoding.combine_mask(mask_hi, mask_lo)).count("1")
+    best = _score_key(objective, threshold, total, 0, 0, 0)
+
+    def recurse(idx: int, used: int, points: int, jokers: int) -> None:
+        nonlocal best
+        best = max(best, _score_key(objective, threshold, total, bin(used).count("1"), points, jokers))
+        for nxt in range(idx, len(melds)):
+            mask, meld_points, meld_jokers = melds[nxt]
+            if used & mask == 0:
+                recurse(nxt + 1, used | mask, points + meld_points, jokers + meld_jokers)
+
+    recurse(0, 0, 0, 0)
+    return best
+
+
+_FIXED_HANDS: list[list[int]] = [
+    # Overlapping runs and sets around the 5s with a joker.
+    [encoding.encode_standard_card(0, rank, 0) for rank in range(2, 8)]
+    + [encoding.encode_standard_card(suit, 4, 0) for suit in (1, 2)]
+    + [encoding.JOKER_IDS[0]],
+    # Two copies of a short run plus a set of queens.
+    [encoding.encode_standard_card(1, rank, copy) for rank in (6, 7, 8) for copy in (0, 1)]
+    + [encoding.encode_standard_card(suit, 11, 0) for suit in range(3)],
+    # Scattered cards where only a couple of melds exist.
+    [encoding.encode_standard_card(suit, rank, 0) for suit, rank in ((0, 0), (1, 0), (2, 0), (3, 5))]
+    + [encoding.encode_standard_card(3, rank, 1) for rank in (9, 10, 11)],
+]
+
+
 def test_enumerate_melds_detects_runs_and_sets() -> None:
     # Run: hearts A-2-3, Set: three 7s, all in copy 0
     run_cards = [
@@ -74,3 +138,1597 @@ def test_best_cover_for_go_out_covers_fourteen_cards() -> None:
     # 5-card straight twice (10+2+3+4+5 = 24 points each) + kings (10 each)
     expected_points = (10 + 2 + 3 + 4 + 5) + (6 + 7 + 8 + 9 + 10) + (10 * 4)
     assert total_points == expected_points
+
+
+def test_enumerate_melds_fills_run_gap_with_joker() -> None:
+    # 5S, Joker, 7S: the joker stands in for 6S
+    five = encoding.encode_standard_card(0, 4, 0)
+    seven = encoding.encode_standard_card(0, 6, 0)
+    joker = encoding.JOKER_IDS[0]
+    mask_hi, mask_lo = _mask_from_cards([five, seven, joker])
+
+    melds = enumerate_melds(mask_hi, mask_lo)
+    runs = [m for m in melds if _cards_from_meld(m.mask_hi, m.mask_lo) == {five, seven, joker}]
+
+    assert len(runs) == 1
+    assert runs[0].jokers_used == 1
+    assert runs[0].points == 5 + 6 + 7
+
+
+def test_enumerate_melds_ace_modes() -> None:
+    queen, king, ace, two = (encoding.encode_standard_card(2, rank, 0) for rank in (11, 12, 0, 1))
+    mask_hi, mask_lo = _mask_from_cards([queen, king, ace, two])
+
+    def run_sets(ace_mode: int) -> list[set[int]]:
+        melds = enumerate_melds(mask_hi, mask_lo, ace_mode)
+        return [_cards_from_meld(m.mask_hi, m.mask_lo) for m in melds]
+
+    assert {queen, king, ace} not in run_sets(ACE_LOW)
+    assert {queen, king, ace} in run_sets(ACE_HIGH_LOW)
+    assert {king, ace, two} not in run_sets(ACE_HIGH_LOW)
+    assert {king, ace, two} in run_sets(ACE_WRAP)
+    assert {queen, king, ace, two} in run_sets(ACE_WRAP)
+
+
+def test_mask_to_cards_lists_sorted_ids_including_jokers() -> None:
+    from konkan_melds import mask_to_cards
+
+    cards = [encoding.JOKER_IDS[1], 70, 3, encoding.JOKER_IDS[0], 63, 64]
+    mask_hi, mask_lo = _mask_from_cards(cards)
+
+    assert mask_to_cards(mask_hi, mask_lo) == sorted(cards)
+
+
+def test_best_cover_reports_deadwood_cards() -> None:
+    run_cards = [encoding.encode_standard_card(1, rank, 0) for rank in (3, 4, 5)]
+    loose_cards = [encoding.encode_standard_card(0, 9, 0), encoding.encode_standard_card(3, 1, 1)]
+    mask_hi, mask_lo = _mask_from_cards(run_cards + loose_cards)
+
+    cover = best_cover_to_threshold(mask_hi, mask_lo, threshold=0)
+    assert cover.covered_cards == 3
+    assert cover.deadwood == sorted(loose_cards)
+
+
+def test_best_cover_splits_run_when_its_end_completes_a_set() -> None:
+    # 3S-4S-5S-6S swallows 3S-4S-5S, but only the shorter run frees 6S for the 6s.
+    spades = [encoding.encode_standard_card(0, rank, 0) for rank in (2, 3, 4, 5)]
+    sixes = [encoding.encode_standard_card(suit, 5, 0) for suit in (1, 2)]
+    mask_hi, mask_lo = _mask_from_cards(spades + sixes)
+
+    cover = best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+    assert cover.covered_cards == 6
+    assert sorted(len(_cards_from_meld(m.mask_hi, m.mask_lo)) for m in cover.melds) == [3, 3]
+
+
+def test_best_cover_reports_score_fields() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import parse_hand
+
+    mask_hi, mask_lo = parse_hand(["3S", "4S", "5S", "KH", "2D"])
+    cover = native_best_cover(mask_hi, mask_lo, OBJ_MIN_DEADWOOD, 10)
+    assert cover.deadwood_count == len(cover.deadwood) == 2
+    assert cover.meets_threshold
+    assert not cover.target_met
+
+    assert not native_best_cover(mask_hi, mask_lo, OBJ_MIN_DEADWOOD, 13).meets_threshold
+
+
+def test_best_cover_target_cards_sets_the_first_14_target() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import parse_hand
+
+    mask_hi, mask_lo = parse_hand(["3S", "4S", "5S", "KH", "KD", "KC", "2D"])
+    assert not native_best_cover(mask_hi, mask_lo, OBJ_FIRST_14, 0).target_met
+
+    cover = native_best_cover(mask_hi, mask_lo, OBJ_FIRST_14, 0, target_cards=6)
+    assert cover.target_met and cover.covered_cards == 6
+    assert not native_best_cover(mask_hi, mask_lo, OBJ_FIRST_14, 0, target_cards=7).target_met
+
+
+def test_soft_deadwood_prefers_leftovers_near_a_meld() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import mask_to_strings, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "7H", "7D", "9H"])
+
+    # Both covers leave three cards; the set of sevens scores more points.
+    plain = native_best_cover(mask_hi, mask_lo, OBJ_MIN_DEADWOOD, 0)
+    assert sorted(mask_to_strings(plain.melds[0].mask_hi, plain.melds[0].mask_lo)) == [
+        "7D",
+        "7H",
+        "7S",
+    ]
+
+    # The run leaves 7H 7D (a pair) and 7H 9H (a run fragment) in hand.
+    soft = native_best_cover(mask_hi, mask_lo, OBJ_MIN_DEADWOOD_SOFT, 0)
+    assert soft.deadwood_count == plain.deadwood_count == 3
+    assert mask_to_strings(soft.melds[0].mask_hi, soft.melds[0].mask_lo) == ["5S", "6S", "7S"]
+
+
+def test_safe_max_cards_leaves_the_cheapest_top_card() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import card_to_string, mask_to_strings, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["4S", "5S", "6S", "5H", "5D"])
+
+    # Both melds cover three cards for 15 points; the run leaves 5H 5D
+    # behind, the set of fives leaves 4S 6S.
+    safe = native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS_SAFE, 0)
+    assert safe.covered_cards == 3
+    assert mask_to_strings(safe.melds[0].mask_hi, safe.melds[0].mask_lo) == ["4S", "5S", "6S"]
+    assert sorted(card_to_string(cid) for cid in safe.deadwood) == ["5D", "5H"]
+
+
+def test_best_cover_flags_empty_and_joker_only_hands() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import parse_hand
+
+    empty = native_best_cover(0, 0, OBJ_MAX_CARDS, 0)
+    assert empty.empty_hand
+    assert not empty.jokers_only
+
+    jokers = native_best_cover(*parse_hand(["J1", "J2"]), OBJ_MAX_CARDS, 0)
+    assert jokers.jokers_only
+    assert not jokers.empty_hand
+    assert jokers.deadwood_count == 2
+
+    stranded = native_best_cover(*parse_hand(["KH", "J1"]), OBJ_MAX_CARDS, 0)
+    assert not stranded.jokers_only
+    assert not stranded.empty_hand
+
+
+def test_best_cover_max_points_prefers_value_over_card_count() -> None:
+    # The joker either completes 2S-J-4S-5S (4 cards, 14 points) or KH-KD-J (3 cards, 30 points).
+    spades = [encoding.encode_standard_card(0, rank, 0) for rank in (1, 3, 4)]
+    kings = [encoding.encode_standard_card(suit, 12, 0) for suit in (1, 2)]
+    joker = encoding.JOKER_IDS[0]
+    mask_hi, mask_lo = _mask_from_cards(spades + kings + [joker])
+
+    by_cards = best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+    assert by_cards.covered_cards == 4
+    assert by_cards.total_points == 2 + 3 + 4 + 5
+
+    by_points = best_cover(mask_hi, mask_lo, OBJ_MAX_POINTS, 0)
+    assert by_points.covered_cards == 3
+    assert by_points.total_points == 30
+
+
+@pytest.mark.parametrize(
+    "objective, threshold",
+    [
+        (OBJ_MAX_CARDS, 0),
+        (OBJ_MIN_DEADWOOD, 0),
+        (OBJ_MIN_DEADWOOD, 40),
+        (OBJ_FIRST_14, 0),
+        (OBJ_MIN_JOKERS, 0),
+    ],
+)
+def test_best_cover_matches_exhaustive_search(objective: int, threshold: int) -> None:
+    for cards in _FIXED_HANDS:
+        mask_hi, mask_lo = _mask_from_cards(cards)
+        cover = best_cover(mask_hi, mask_lo, objective, threshold)
+        total = len(cards)
+        key = _score_key(
+            objective, threshold, total, cover.covered_cards, cover.total_points, cover.used_jokers
+        )
+        assert key == _exhaustive_best_key(mask_hi, mask_lo, objective, threshold)
+
+
+def test_best_cover_parallel_matches_sequential() -> None:
+    from konkan_melds import best_cover_parallel
+
+    for cards in _FIXED_HANDS:
+        mask_hi, mask_lo = _mask_from_cards(cards)
+        for objective in (OBJ_MAX_CARDS, OBJ_MIN_DEADWOOD, OBJ_FIRST_14, OBJ_MAX_POINTS):
+            sequential = best_cover(mask_hi, mask_lo, objective, 30)
+            parallel = best_cover_parallel(mask_hi, mask_lo, objective, 30)
+            assert [(m.mask_hi, m.mask_lo) for m in parallel.melds] == [
+                (m.mask_hi, m.mask_lo) for m in sequential.melds
+            ]
+            assert parallel.total_points == sequential.total_points
+
+
+def test_best_cover_dp_matches_the_search() -> None:
+    from konkan_melds import best_cover_dp, parse_hand
+
+    # The last hand holds 17 cards, past the subset table.
+    spades = ["AS", "2S", "3S", "4S", "5S", "6S", "7S", "8S", "9S", "TS", "JS"]
+    wide = parse_hand(spades + ["2H", "3H", "4H", "2D", "3D", "4D"])
+    hands = [_mask_from_cards(cards) for cards in _FIXED_HANDS] + [wide]
+    objectives = (OBJ_MAX_CARDS, OBJ_MIN_DEADWOOD, OBJ_FIRST_14, OBJ_MAX_POINTS, OBJ_MIN_JOKERS)
+    for mask_hi, mask_lo in hands:
+        for objective in objectives:
+            search = best_cover(mask_hi, mask_lo, objective, 30)
+            dp = best_cover_dp(mask_hi, mask_lo, objective, 30)
+            assert [(m.mask_hi, m.mask_lo) for m in dp.melds] == [
+                (m.mask_hi, m.mask_lo) for m in search.melds
+            ]
+            assert (dp.total_points, dp.deadwood) == (search.total_points, search.deadwood)
+
+
+def test_best_single_meld_follows_the_objective() -> None:
+    from konkan_melds import best_single_meld, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["3S", "4S", "5S", "6S", "KH", "KD", "KC", "J1"])
+    by_points = best_single_meld(mask_hi, mask_lo, OBJ_MAX_POINTS)
+    assert by_points is not None
+    assert _cards_from_meld(by_points.mask_hi, by_points.mask_lo) == _cards_from_meld(
+        *parse_hand(["KH", "KD", "KC", "J1"])
+    )
+    by_cards = best_single_meld(mask_hi, mask_lo, OBJ_MAX_CARDS)
+    assert by_cards is not None
+    assert _cards_from_meld(by_cards.mask_hi, by_cards.mask_lo) == _cards_from_meld(
+        *parse_hand(["3S", "4S", "5S", "6S", "J1"])
+    )
+    natural = best_single_meld(mask_hi, mask_lo, OBJ_MIN_JOKERS)
+    assert natural is not None and natural.jokers_used == 0
+    assert len(_cards_from_meld(natural.mask_hi, natural.mask_lo)) == 4
+    assert best_single_meld(*parse_hand(["3S", "9H", "KD"]), OBJ_MAX_POINTS) is None
+
+
+def test_compare_covers_ranks_like_the_search() -> None:
+    from konkan_melds import best_covers, compare_covers, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "8S", "7H", "7D"])
+    covers = best_covers(mask_hi, mask_lo, OBJ_MIN_DEADWOOD, 0, 4)
+    best, worst = covers[0], covers[-1]
+    assert worst.deadwood_count > best.deadwood_count
+    assert compare_covers(best, best, OBJ_MIN_DEADWOOD) == 0
+    assert compare_covers(best, worst, OBJ_MIN_DEADWOOD) == 1
+    assert compare_covers(worst, best, OBJ_MIN_DEADWOOD) == -1
+    for cover in covers[1:]:
+        assert compare_covers(cover, best, OBJ_MIN_DEADWOOD) <= 0
+
+
+def test_zero_threshold_never_prefers_the_empty_cover() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "9H", "9D", "9C", "KD"])
+    cover = native_best_cover(mask_hi, mask_lo, OBJ_MIN_DEADWOOD, 0)
+    assert cover.covered_cards == 6 and cover.meets_threshold
+    # Melds worth less than nothing still beat melding nothing at all.
+    penalties = native_best_cover(mask_hi, mask_lo, OBJ_MIN_DEADWOOD, 0, rank_points=[-1] * 13)
+    assert penalties.covered_cards == 6
+    assert penalties.total_points == -6 and penalties.meets_threshold
+
+
+def test_best_cover_batch_matches_single_calls() -> None:
+    from konkan_melds import best_cover_batch
+
+    hands = [_mask_from_cards(cards) for cards in _FIXED_HANDS]
+    batch = best_cover_batch(hands, OBJ_MIN_DEADWOOD, 30)
+    assert len(batch) == len(hands)
+    for (mask_hi, mask_lo), result in zip(hands, batch):
+        single = best_cover(mask_hi, mask_lo, OBJ_MIN_DEADWOOD, 30)
+        assert [(m.mask_hi, m.mask_lo) for m in result.melds] == [
+            (m.mask_hi, m.mask_lo) for m in single.melds
+        ]
+        assert result.total_points == single.total_points
+
+
+def test_best_cover_joker_limited_banks_jokers_over_the_cap() -> None:
+    from konkan_melds import best_cover_joker_limited, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["7H", "7D", "KS", "KH", "3S", "4S", "5S", "J1", "J2"])
+    unlimited = best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+    assert unlimited.used_jokers == 2
+    assert unlimited.covered_cards == 9
+
+    limited = best_cover_joker_limited(mask_hi, mask_lo, 1, OBJ_MAX_CARDS, 0)
+    assert limited.used_jokers == 1
+    assert limited.covered_cards == 6
+
+    natural = best_cover_joker_limited(mask_hi, mask_lo, 0, OBJ_MAX_CARDS, 0)
+    assert natural.used_jokers == 0
+    assert natural.covered_cards == 3
+
+
+def test_best_cover_pure_keeps_every_joker() -> None:
+    from konkan_melds import best_cover_pure, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["7H", "7D", "KS", "KH", "3S", "4S", "5S", "J1", "J2"])
+    assert best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0).covered_cards == 9
+
+    pure = best_cover_pure(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+    assert pure.used_jokers == 0
+    assert pure.covered_cards == 3
+    assert pure.melds and all(m.is_pure and m.jokers_used == 0 for m in pure.melds)
+
+    wild_hi, wild_lo = parse_hand(["7H", "7D", "8S", "9S", "J1"])
+    assert best_cover_pure(wild_hi, wild_lo, OBJ_MAX_CARDS, 0).melds == []
+
+
+def test_best_cover_delta_tracks_single_card_changes() -> None:
+    from konkan_melds import best_cover_delta, enumerate_melds as native_enumerate_melds
+    from konkan_melds import parse_card, parse_hand
+
+    hand = ["5S", "6S", "7S", "7H", "7D", "QC", "KC", "J1", "2H"]
+    mask_hi, mask_lo = parse_hand(hand)
+    result = best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+    assert result.candidates == []
+
+    for change, drawn in [("8S", True), ("QC", False), ("JC", True), ("J1", False), ("7S", False)]:
+        hand = hand + [change] if drawn else [card for card in hand if card != change]
+        mask_hi, mask_lo = parse_hand(hand)
+        result = best_cover_delta(result, mask_hi, mask_lo, parse_card(change), OBJ_MAX_CARDS, 0)
+        fresh = best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+
+        assert result.candidates == native_enumerate_melds(mask_hi, mask_lo)
+        assert (result.covered_cards, result.total_points) == (
+            fresh.covered_cards,
+            fresh.total_points,
+        )
+
+
+def test_min_deadwood_matches_best_cover() -> None:
+    from konkan_melds import best_cover_joker_limited, min_deadwood
+
+    for cards in _FIXED_HANDS:
+        mask_hi, mask_lo = _mask_from_cards(cards)
+        cover = best_cover(mask_hi, mask_lo, OBJ_FIRST_14, 0)
+        assert min_deadwood(mask_hi, mask_lo) == len(cards) - cover.covered_cards
+        for max_jokers in (0, 1):
+            limited = best_cover_joker_limited(mask_hi, mask_lo, max_jokers, OBJ_FIRST_14, 0)
+            expected = len(cards) - limited.covered_cards
+            assert min_deadwood(mask_hi, mask_lo, max_jokers) == expected
+
+
+def test_best_cover_timed_reports_expired_budget() -> None:
+    from konkan_melds import best_cover_timed
+
+    mask_hi, mask_lo = _mask_from_cards(_FIXED_HANDS[0])
+
+    expired = best_cover_timed(mask_hi, mask_lo, OBJ_MAX_CARDS, 0, 0)
+    assert expired.timed_out
+
+    relaxed = best_cover_timed(mask_hi, mask_lo, OBJ_MAX_CARDS, 0, 60_000)
+    reference = best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+    assert not relaxed.timed_out
+    assert relaxed.covered_cards == reference.covered_cards
+    assert relaxed.total_points == reference.total_points
+
+
+def test_best_covers_returns_distinct_ranked_layouts() -> None:
+    from konkan_melds import best_covers, parse_hand
+
+    # Three layouts over overlapping sevens, none covering another's cards.
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "7H", "7D", "7C", "8H", "9H"])
+    covers = best_covers(mask_hi, mask_lo, OBJ_MAX_CARDS, 0, 3)
+    reference = best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+
+    assert len(covers) == 3
+    assert covers[0].covered_cards == reference.covered_cards
+    assert covers[0].total_points == reference.total_points
+    unions = set()
+    for cover in covers:
+        union = 0
+        for meld in cover.melds:
+            union |= encoding.combine_mask(meld.mask_hi, meld.mask_lo)
+        unions.add(union)
+    assert len(unions) == len(covers)
+    assert all(inner == outer or inner & ~outer for inner in unions for outer in unions)
+    keys = [(c.covered_cards, c.total_points) for c in covers]
+    assert keys == sorted(keys, reverse=True)
+
+
+def test_all_maximal_covers_lists_every_unextendable_layout() -> None:
+    from konkan_melds import all_maximal_covers, parse_hand
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "8S", "KD"])
+    covers = all_maximal_covers(mask_hi, mask_lo, 10)
+    layouts = {
+        tuple(sorted(_cards_from_meld(m.mask_hi, m.mask_lo))) for c in covers for m in c.melds
+    }
+
+    assert len(covers) == 3 and all(len(c.melds) == 1 for c in covers)
+    assert layouts == {(4, 5, 6), (5, 6, 7), (4, 5, 6, 7)}
+    assert covers[0].covered_cards == 4
+    assert len(all_maximal_covers(mask_hi, mask_lo, 2)) == 2
+
+    mask_hi, mask_lo = parse_hand(["7S", "7H", "7D", "7C", "8S", "9S"])
+    for cover in all_maximal_covers(mask_hi, mask_lo, 50):
+        used = 0
+        for meld in cover.melds:
+            mask = encoding.combine_mask(meld.mask_hi, meld.mask_lo)
+            assert used & mask == 0
+            used |= mask
+        others = native_enumerate_melds(mask_hi, mask_lo)
+        assert all(encoding.combine_mask(m.mask_hi, m.mask_lo) & used for m in others)
+
+
+def test_enumerate_melds_single_deck_layout_moves_jokers() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+
+    sevens = [encoding.encode_standard_card(suit, 6, 0) for suit in (0, 1)]
+    # ID 52 is the second ace of spades in two decks but the first joker in one.
+    mask_hi, mask_lo = _mask_from_cards([*sevens, 52])
+
+    assert native_enumerate_melds(mask_hi, mask_lo) == []
+    single_deck = native_enumerate_melds(mask_hi, mask_lo, num_decks=1, num_jokers=2)
+    assert [m.jokers_used for m in single_deck] == [1]
+
+    with pytest.raises(ValueError):
+        native_enumerate_melds(mask_hi, mask_lo, num_decks=3)
+
+
+def test_custom_rank_points_rescore_melds() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+
+    aces = [encoding.encode_standard_card(suit, 0, 0) for suit in range(3)]
+    mask_hi, mask_lo = _mask_from_cards(aces)
+    ace_eleven = [11] + list(encoding.POINTS[1:])
+
+    melds = native_enumerate_melds(mask_hi, mask_lo, rank_points=ace_eleven)
+    assert [m.points for m in melds] == [33]
+    cover = native_best_cover(mask_hi, mask_lo, OBJ_MAX_POINTS, 0, rank_points=ace_eleven)
+    assert cover.total_points == 33
+    assert native_best_cover(mask_hi, mask_lo, OBJ_MAX_POINTS, 0).total_points == 30
+
+
+def test_best_cover_min_first_points_gates_small_covers() -> None:
+    from konkan_melds import best_cover as native_best_cover
+
+    # The joker either completes 2S-J-4S-5S (4 cards, 14 points) or KH-KD-J (3 cards, 30 points).
+    spades = [encoding.encode_standard_card(0, rank, 0) for rank in (1, 3, 4)]
+    kings = [encoding.encode_standard_card(suit, 12, 0) for suit in (1, 2)]
+    mask_hi, mask_lo = _mask_from_cards(spades + kings + [encoding.JOKER_IDS[0]])
+
+    ungated = native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+    assert ungated.total_points == 14
+
+    gated = native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0, min_first_points=30)
+    assert gated.covered_cards == 3
+    assert gated.total_points == 30
+
+    # When nothing reaches the minimum the objective still picks among the rest.
+    unreachable = native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0, min_first_points=81)
+    assert unreachable.total_points == 14
+
+
+def test_deadwood_points_counts_uncovered_cards_and_jokers() -> None:
+    from konkan_melds import deadwood_points
+
+    run_cards = [encoding.encode_standard_card(1, rank, 0) for rank in (3, 4, 5)]
+    leftovers = [encoding.encode_standard_card(0, 0, 0), encoding.encode_standard_card(2, 8, 1)]
+    joker = encoding.JOKER_IDS[1]
+    hand_hi, hand_lo = _mask_from_cards(run_cards + leftovers + [joker])
+    covered_hi, covered_lo = _mask_from_cards(run_cards)
+
+    assert deadwood_points(hand_hi, hand_lo, covered_hi, covered_lo) == 10 + 9 + 25
+    assert deadwood_points(hand_hi, hand_lo, covered_hi, covered_lo, joker_penalty=0) == 19
+
+
+def test_deadwood_rejects_a_joker_penalty_that_could_overflow() -> None:
+    from konkan_melds import deadwood_breakdown, deadwood_points, parse_hand
+
+    hand_hi, hand_lo = parse_hand(["5S", "6S", "7S", "KH", "J1", "J2"])
+    largest = (2**31 - 1) // 128
+    assert deadwood_points(hand_hi, hand_lo, 0, 0, joker_penalty=largest) == 28 + 2 * largest
+    for joker_penalty in (2**31 - 1, -1):
+        with pytest.raises(ValueError, match="joker_penalty"):
+            deadwood_points(hand_hi, hand_lo, 0, 0, joker_penalty=joker_penalty)
+        with pytest.raises(ValueError, match="joker_penalty"):
+            deadwood_breakdown(hand_hi, hand_lo, 0, 0, joker_penalty=joker_penalty)
+
+
+def test_deadwood_breakdown_groups_leftovers_by_rank() -> None:
+    from konkan_melds import deadwood_breakdown, deadwood_points, parse_hand
+
+    hand_hi, hand_lo = parse_hand(["3H", "4H", "5H", "AS", "AD", "9C#2", "J2"])
+    covered_hi, covered_lo = parse_hand(["3H", "4H", "5H"])
+
+    # Jokers come last under the rank after the king.
+    breakdown = deadwood_breakdown(hand_hi, hand_lo, covered_hi, covered_lo)
+    assert breakdown == [(0, 2, 20), (8, 1, 9), (13, 1, 25)]
+    assert sum(points for _, _, points in breakdown) == deadwood_points(
+        hand_hi, hand_lo, covered_hi, covered_lo
+    )
+
+
+def test_parse_card_and_hand_notation() -> None:
+    from konkan_melds import parse_card, parse_hand
+
+    assert parse_card("TH") == encoding.encode_standard_card(1, 9, 0)
+    assert parse_card("2S") == encoding.encode_standard_card(0, 1, 0)
+    assert parse_card("KC#2") == encoding.encode_standard_card(3, 12, 1)
+    assert parse_card("J1") == encoding.JOKER_IDS[0]
+    assert parse_card("J2") == encoding.JOKER_IDS[1]
+
+    hand = ["AS", "2S", "3S", "J2"]
+    assert parse_hand(hand) == _mask_from_cards(parse_card(card) for card in hand)
+
+    for bad in ("1S", "TX", "TH#3", "J3", "", "QHS"):
+        with pytest.raises(ValueError):
+            parse_card(bad)
+    with pytest.raises(ValueError, match="twice"):
+        parse_hand(["AS", "AS"])
+
+
+def test_card_notation_round_trips_every_card() -> None:
+    from konkan_melds import card_to_string, mask_to_strings, parse_card
+
+    for card_id in range(encoding.DECK_CARD_COUNT):
+        assert parse_card(card_to_string(card_id)) == card_id
+
+    assert card_to_string(encoding.encode_standard_card(1, 9, 1)) == "TH#2"
+    assert card_to_string(encoding.JOKER_IDS[0]) == "J1"
+    mask_hi, mask_lo = _mask_from_cards([0, encoding.JOKER_IDS[1], 13])
+    assert mask_to_strings(mask_hi, mask_lo) == ["AS", "AH", "J2"]
+    with pytest.raises(ValueError):
+        card_to_string(encoding.DECK_CARD_COUNT)
+
+
+def test_meld_and_cover_repr_show_cards() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "7S", "J1", "KH", "KD", "KC"])
+    cover = native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+
+    lines = repr(cover).splitlines()
+    assert lines[0] == "CoverResult(covered_cards=6, total_points=48, used_jokers=1"
+    assert "    Meld(run [5S 7S J1], points=18, jokers_used=1)" in lines
+    assert "    Meld(set [KH KD KC], points=30, jokers_used=0)" in lines
+    assert str(cover.melds[0]) == repr(cover.melds[0])
+
+
+def test_meld_equality_hash_and_order_follow_sort_key() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "8S", "7H", "7D", "J1"])
+    first = native_enumerate_melds(mask_hi, mask_lo)
+    second = native_enumerate_melds(mask_hi, mask_lo)
+
+    assert first == second
+    assert len(set(first) | set(second)) == len(first)
+    assert sorted(reversed(first)) == first
+    assert first[0] < first[-1]
+    assert first[0] != first[-1]
+
+
+def test_cover_json_round_trip_keeps_masks_as_strings() -> None:
+    import json
+
+    from konkan_melds import CoverResult
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import parse_hand
+
+    if not hasattr(CoverResult, "to_json"):
+        pytest.skip("konkan_melds built without the serde feature")
+
+    mask_hi, mask_lo = parse_hand(["5S", "7S", "J1", "KH", "KD", "KC"])
+    cover = native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+    payload = json.loads(cover.to_json())
+
+    assert all(isinstance(meld["mask_lo"], str) for meld in payload["melds"])
+    restored = CoverResult.from_json(cover.to_json())
+    assert restored.melds == cover.melds
+    assert repr(restored) == repr(cover)
+    assert type(cover.melds[0]).from_json(cover.melds[0].to_json()) == cover.melds[0]
+
+
+def test_iter_melds_streams_the_same_melds_as_enumerate() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import iter_melds, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "8S", "7H", "7D", "7C", "J1"])
+    streamed = iter_melds(mask_hi, mask_lo, ace_mode=ACE_WRAP)
+
+    assert iter(streamed) is streamed
+    first = next(streamed)
+    assert sorted([first, *streamed]) == native_enumerate_melds(
+        mask_hi, mask_lo, ace_mode=ACE_WRAP
+    )
+    with pytest.raises(StopIteration):
+        next(streamed)
+
+
+def test_enumerate_melds_sets_may_repeat_a_suit_across_decks() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import mask_to_strings, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["KS", "KS#2", "KH"])
+    melds = native_enumerate_melds(mask_hi, mask_lo)
+
+    assert [mask_to_strings(m.mask_hi, m.mask_lo) for m in melds] == [
+        ["KS", "KH", "KS#2"]
+    ]
+    assert melds[0].jokers_used == 0
+    assert melds[0].points == 30
+
+
+def test_enumerate_melds_from_ids_matches_the_mask_api() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import enumerate_melds_from_ids, parse_card, parse_hand
+
+    cards = ["5S", "6S", "7S", "7H", "7D", "J1"]
+    ids = [parse_card(card) for card in cards]
+    assert enumerate_melds_from_ids(ids) == native_enumerate_melds(*parse_hand(cards))
+
+    with pytest.raises(ValueError, match="listed twice"):
+        enumerate_melds_from_ids([ids[0], ids[0]])
+    with pytest.raises(ValueError, match="outside"):
+        enumerate_melds_from_ids([106])
+
+
+def test_enumerate_runs_and_sets_split_the_combined_output() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import enumerate_runs, enumerate_sets, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "8S", "7H", "7D", "J1"])
+    runs = enumerate_runs(mask_hi, mask_lo)
+    sets = enumerate_sets(mask_hi, mask_lo)
+
+    assert runs and all(m.kind == 1 for m in runs)
+    assert sets and all(m.kind == 0 for m in sets)
+
+    def key(meld: object) -> tuple[int, ...]:
+        return (meld.mask_hi, meld.mask_lo, meld.kind, meld.jokers_used, meld.points)
+
+    assert sorted(runs + sets, key=key) == native_enumerate_melds(mask_hi, mask_lo)
+
+
+def test_enumerate_runs_collapse_copies_keeps_lowest_ids() -> None:
+    from konkan_melds import enumerate_runs, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["7S", "7S#2", "8S", "8S#2", "9S", "J1"])
+    runs = enumerate_runs(mask_hi, mask_lo)
+    collapsed = enumerate_runs(mask_hi, mask_lo, collapse_copies=True)
+
+    assert len(collapsed) < len(runs)
+    lowest = _cards_from_meld(*parse_hand(["7S", "8S", "9S", "J1"]))
+    for meld in collapsed:
+        assert _cards_from_meld(meld.mask_hi, meld.mask_lo) <= lowest
+    assert {(m.mask_hi, m.mask_lo) for m in collapsed} <= {(m.mask_hi, m.mask_lo) for m in runs}
+
+
+def test_enumerate_sets_dedup_joker_assignments_keeps_lowest_joker() -> None:
+    from konkan_melds import enumerate_sets, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["7S", "7H", "J1", "J2"])
+    sets = enumerate_sets(mask_hi, mask_lo)
+    deduped = enumerate_sets(mask_hi, mask_lo, dedup_joker_assignments=True)
+
+    # 7S 7H J1 and 7S 7H J2 collapse into the first.
+    assert len(sets) == 5
+    assert len(deduped) == 4
+    assert sorted(meld.joker_ids for meld in deduped) == [[104], [104, 105], [104, 105], [104, 105]]
+
+
+def test_max_jokers_per_meld_drops_melds_needing_two() -> None:
+    from konkan_melds import enumerate_runs, enumerate_sets, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["7S", "7H", "7D", "KS", "KH", "J1", "J2"])
+    assert any(m.jokers_used == 2 for m in enumerate_sets(mask_hi, mask_lo))
+
+    capped = enumerate_sets(mask_hi, mask_lo, max_jokers_per_meld=1)
+    assert all(m.jokers_used <= 1 for m in capped)
+    card_sets = [_cards_from_meld(m.mask_hi, m.mask_lo) for m in capped]
+    # Three sevens take a fourth card from one joker; two kings cannot.
+    assert _cards_from_meld(*parse_hand(["7S", "7H", "7D", "J1"])) in card_sets
+    assert _cards_from_meld(*parse_hand(["KS", "KH", "J1"])) in card_sets
+    assert _cards_from_meld(*parse_hand(["KS", "KH", "J1", "J2"])) not in card_sets
+
+    run_hi, run_lo = parse_hand(["5S", "8S", "J1", "J2"])
+    assert len(enumerate_runs(run_hi, run_lo)) > 0
+    assert enumerate_runs(run_hi, run_lo, max_jokers_per_meld=1) == []
+
+
+def test_include_partials_lists_pairs_one_card_short() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "7S", "9H", "9D", "KC", "J1"])
+    assert native_enumerate_melds(mask_hi, mask_lo) != []
+    melds = native_enumerate_melds(mask_hi, mask_lo, include_partials=True)
+    partials = [m for m in melds if m.kind == 2]
+    # The joker and the lone king join no pair.
+    assert {frozenset(_cards_from_meld(m.mask_hi, m.mask_lo)) for m in partials} == {
+        frozenset(_cards_from_meld(*parse_hand(pair))) for pair in (["5S", "7S"], ["9H", "9D"])
+    }
+    assert all(m.cards_needed == 1 and m.jokers_used == 0 for m in partials)
+    assert [m for m in melds if m.kind != 2] == native_enumerate_melds(mask_hi, mask_lo)
+    assert {m.points for m in partials} == {12, 18}
+
+
+def test_module_exports_objective_and_kind_constants() -> None:
+    import konkan_melds
+    from konkan import melds
+
+    for name in [
+        "OBJ_MAX_CARDS",
+        "OBJ_MIN_DEADWOOD",
+        "OBJ_FIRST_14",
+        "OBJ_MAX_POINTS",
+        "OBJ_MIN_JOKERS",
+        "OBJ_MIN_DEADWOOD_SOFT",
+        "OBJ_WEIGHTED",
+        "OBJ_MAX_CARDS_SAFE",
+    ]:
+        assert getattr(konkan_melds, name) == getattr(melds, name), name
+
+    mask_hi, mask_lo = konkan_melds.parse_hand(["5S", "6S", "7S", "9H", "9D", "9C"])
+    cover = konkan_melds.best_cover(mask_hi, mask_lo, konkan_melds.OBJ_MIN_DEADWOOD, 0)
+    kinds = sorted(m.kind for m in cover.melds)
+    assert kinds == sorted([konkan_melds.KIND_RUN, konkan_melds.KIND_SET])
+    partials = konkan_melds.enumerate_melds(mask_hi, mask_lo, include_partials=True)
+    assert any(m.kind == konkan_melds.KIND_PARTIAL for m in partials)
+
+
+def test_melds_containing_matches_a_filtered_enumeration() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import melds_containing, parse_card, parse_hand
+
+    hand = ["5S", "6S", "7S", "8S", "7H", "7D", "7S#2", "KC", "J1"]
+    mask_hi, mask_lo = parse_hand(hand)
+    everything = native_enumerate_melds(mask_hi, mask_lo)
+    for token in hand:
+        card_id = parse_card(token)
+        expected = [m for m in everything if card_id in _cards_from_meld(m.mask_hi, m.mask_lo)]
+        assert melds_containing(mask_hi, mask_lo, card_id) == expected, token
+    assert melds_containing(mask_hi, mask_lo, parse_card("9D")) == []
+    with pytest.raises(ValueError, match="outside"):
+        melds_containing(mask_hi, mask_lo, 106)
+
+
+def test_meld_constructor_checks_its_fields() -> None:
+    from konkan_melds import KIND_RUN, KIND_SET, Meld, best_single_meld, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "J1"])
+    meld = Meld(mask_hi, mask_lo, 18, 1, KIND_RUN)
+    assert (meld.points, meld.jokers_used, meld.kind) == (18, 1, KIND_RUN)
+    assert meld.joker_ids == [104] and not meld.is_pure
+    assert meld == best_single_meld(mask_hi, mask_lo, OBJ_MAX_POINTS)
+
+    with pytest.raises(ValueError, match="kind"):
+        Meld(mask_hi, mask_lo, 18, 1, 9)
+    with pytest.raises(ValueError, match="jokers"):
+        Meld(mask_hi, mask_lo, 18, 4, KIND_SET)
+    with pytest.raises(ValueError, match="outside"):
+        Meld(1 << 50, 0, 0, 0, KIND_SET)
+
+
+def test_runs_pick_the_copy_the_other_run_leaves() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import parse_hand
+
+    # Each run needs one of the two sixes, whichever deck it came from.
+    mask_hi, mask_lo = parse_hand(["4S", "5S", "6S", "6S#2", "7S#2", "8S"])
+    cover = native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+    assert cover.covered_cards == 6
+    assert len(cover.melds) == 2
+
+
+def test_discard_safety_counts_the_opponent_cards_a_discard_connects_with():
+    from konkan_melds import discard_safety, parse_card, parse_hand
+
+    picked_hi, picked_lo = parse_hand(["7H", "7D", "5S"])
+    assert discard_safety(parse_card("7S"), picked_hi, picked_lo) == -5
+    assert discard_safety(parse_card("6S"), picked_hi, picked_lo) == -2
+    assert discard_safety(parse_card("KC"), picked_hi, picked_lo) == 0
+    assert discard_safety(104, picked_hi, picked_lo) == -10
+    with pytest.raises(ValueError):
+        discard_safety(106, picked_hi, picked_lo)
+
+
+def test_discard_safety_counts_copies_and_aces_above_the_king():
+    from konkan_melds import discard_safety, parse_card, parse_hand
+
+    # The other deck's seven of spades sets with 7S like any other seven.
+    assert discard_safety(parse_card("7S"), *parse_hand(["7S#2"])) == -2
+    picked_hi, picked_lo = parse_hand(["KS", "QS"])
+    assert discard_safety(parse_card("AS"), picked_hi, picked_lo) == 0
+    assert discard_safety(parse_card("AS"), picked_hi, picked_lo, ACE_HIGH_LOW) == -3
+    assert discard_safety(parse_card("2S"), picked_hi, picked_lo, ACE_HIGH_LOW) == 0
+    assert discard_safety(parse_card("2S"), picked_hi, picked_lo, ACE_WRAP) == -1
+
+
+def test_solver_reuses_buffers_across_hands() -> None:
+    from konkan_melds import Solver
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import parse_hand
+
+    solver = Solver()
+    hands = [
+        ["5S", "6S", "7S", "8S", "7H", "7D", "J1"],
+        ["KS", "KS#2", "KH", "QS", "JS", "J2"],
+        [],
+        ["5S", "6S", "7S", "8S", "7H", "7D", "J1"],
+    ]
+    for hand in hands:
+        mask_hi, mask_lo = parse_hand(hand)
+        assert solver.enumerate(mask_hi, mask_lo) == native_enumerate_melds(mask_hi, mask_lo)
+
+    wrapping = Solver(ace_mode=2)
+    mask_hi, mask_lo = parse_hand(["KS", "AS", "2S"])
+    assert wrapping.enumerate(mask_hi, mask_lo) == native_enumerate_melds(
+        mask_hi, mask_lo, ace_mode=2
+    )
+    with pytest.raises(ValueError, match="outside"):
+        solver.enumerate(1 << 42, 0)
+
+
+def test_enumerate_melds_stats_counts_candidates() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import enumerate_melds_stats, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "8S", "7H", "7D", "J1"])
+    melds, stats = enumerate_melds_stats(mask_hi, mask_lo)
+
+    assert melds == native_enumerate_melds(mask_hi, mask_lo)
+    assert stats.runs == sum(1 for m in melds if m.kind == 1)
+    assert stats.sets == sum(1 for m in melds if m.kind == 0)
+    assert stats.longest_run == 5
+    assert stats.duplicates > 0
+
+    _, pure = enumerate_melds_stats(*parse_hand(["5S", "6S", "7S", "8S", "7H", "7D"]))
+    assert (pure.sets, pure.runs, pure.duplicates, pure.longest_run) == (1, 3, 0, 4)
+
+
+def test_meld_limit_truncates_enumeration_and_cover() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import enumerate_melds_stats, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "8S", "7H", "7D"])
+    assert len(native_enumerate_melds(mask_hi, mask_lo)) == 4
+    assert len(native_enumerate_melds(mask_hi, mask_lo, meld_limit=2)) == 2
+
+    _, stats = enumerate_melds_stats(mask_hi, mask_lo, meld_limit=2)
+    assert stats.truncated
+    assert (stats.sets, stats.runs) == (1, 1)
+    _, stats = enumerate_melds_stats(mask_hi, mask_lo)
+    assert not stats.truncated
+
+    # Sets come first, so a single meld leaves only the sevens to cover.
+    cover = native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0, meld_limit=1)
+    assert cover.truncated
+    assert cover.covered_cards == 3
+    assert not native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0).truncated
+
+
+def test_enumerate_melds_set_size_bounds() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import parse_hand
+
+    mask_hi, mask_lo = parse_hand(["KS", "KS#2", "KH", "KD", "KC"])
+
+    def sizes(**kwargs: int) -> list[int]:
+        melds = native_enumerate_melds(mask_hi, mask_lo, **kwargs)
+        return sorted(len(_cards_from_meld(m.mask_hi, m.mask_lo)) for m in melds)
+
+    assert sizes() == [3] * 10 + [4] * 5
+    assert sizes(max_set_size=3) == [3] * 10
+    assert sizes(max_set_size=5) == [3] * 10 + [4] * 5 + [5]
+    assert sizes(min_set_size=5, max_set_size=5) == [5]
+
+    with pytest.raises(ValueError, match="exceeds"):
+        native_enumerate_melds(mask_hi, mask_lo, min_set_size=4, max_set_size=3)
+
+
+def test_melds_flag_purity_and_covers_count_pure_melds() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "7S", "J1", "KH", "KD", "KC"])
+    cover = native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+
+    assert all(meld.is_pure == (meld.jokers_used == 0) for meld in cover.melds)
+    assert sorted(meld.is_pure for meld in cover.melds) == [False, True]
+    assert cover.count_pure_melds() == 1
+
+
+def test_cover_coverage_mask_unions_the_melds() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "KH", "KD", "KC", "2C"])
+    cover = native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+
+    assert cover.coverage_mask() == parse_hand(["5S", "6S", "7S", "KH", "KD", "KC"])
+    assert native_best_cover(*parse_hand(["2C"]), OBJ_MAX_CARDS, 0).coverage_mask() == (0, 0)
+
+
+def test_best_cover_constrained_melds_required_cards() -> None:
+    from konkan_melds import best_cover_constrained, mask_to_strings, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "7H", "7D"])
+
+    required_hi, required_lo = parse_hand(["5S"])
+    cover = best_cover_constrained(
+        mask_hi, mask_lo, OBJ_MAX_CARDS, 0, required_hi, required_lo
+    )
+    assert not cover.infeasible
+    assert [mask_to_strings(m.mask_hi, m.mask_lo) for m in cover.melds] == [
+        ["5S", "6S", "7S"]
+    ]
+
+    # 7S cannot sit in both the run and the set.
+    required_hi, required_lo = parse_hand(["5S", "7H"])
+    cover = best_cover_constrained(
+        mask_hi, mask_lo, OBJ_MAX_CARDS, 0, required_hi, required_lo
+    )
+    assert cover.infeasible
+    assert cover.melds == []
+    assert cover.covered_cards == 0
+    assert len(cover.deadwood) == 5
+
+    # The deck options apply as they do to best_cover.
+    second_copy_hi, second_copy_lo = parse_hand(["5S#2", "6S#2", "7S#2"])
+    cover = best_cover_constrained(
+        second_copy_hi, second_copy_lo, OBJ_MAX_CARDS, 0, second_copy_hi, second_copy_lo
+    )
+    assert cover.covered_cards == 3
+    with pytest.raises(ValueError):
+        best_cover_constrained(
+            second_copy_hi, second_copy_lo, OBJ_MAX_CARDS, 0, 0, 0, num_decks=1
+        )
+    with pytest.raises(ValueError):
+        best_cover_constrained(mask_hi, mask_lo, OBJ_MAX_CARDS, 0, 1 << 60, 0)
+
+
+def test_best_cover_forbidden_joker_forces_natural_cover() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import parse_card, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "8S", "J1"])
+    assert native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0).covered_cards == 5
+
+    forbidden_hi, forbidden_lo = parse_hand(["J1"])
+    cover = native_best_cover(
+        mask_hi,
+        mask_lo,
+        OBJ_MAX_CARDS,
+        0,
+        forbidden_hi=forbidden_hi,
+        forbidden_lo=forbidden_lo,
+    )
+    assert cover.covered_cards == 4
+    assert cover.used_jokers == 0
+    assert all(meld.is_pure for meld in cover.melds)
+    assert cover.deadwood == [parse_card("J1")]
+
+
+def test_best_cover_max_melds_caps_selection() -> None:
+    from konkan_melds import best_cover as native_best_cover
+
+    for hand in _FIXED_HANDS:
+        mask_hi, mask_lo = _mask_from_cards(hand)
+        unconstrained = native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+        hand_melds = set(enumerate_melds(mask_hi, mask_lo))
+
+        previous = unconstrained.covered_cards
+        for max_melds in range(len(unconstrained.melds), 0, -1):
+            capped = native_best_cover(
+                mask_hi, mask_lo, OBJ_MAX_CARDS, 0, max_melds=max_melds
+            )
+            assert len(capped.melds) <= max_melds
+            assert capped.covered_cards <= previous
+            assert set(capped.melds) <= hand_melds
+            used = [
+                card
+                for meld in capped.melds
+                for card in _cards_from_meld(meld.mask_hi, meld.mask_lo)
+            ]
+            assert len(used) == len(set(used)) == capped.covered_cards
+            previous = capped.covered_cards
+
+
+def test_best_cover_min_jokers_trades_coverage_for_natural_melds() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "8S", "J1", "KH", "KD"])
+
+    widest = native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+    assert widest.covered_cards == 7
+    assert widest.used_jokers == 1
+
+    frugal = native_best_cover(mask_hi, mask_lo, OBJ_MIN_JOKERS, 0)
+    assert frugal.used_jokers == 0
+    assert frugal.covered_cards == 4
+    assert frugal.total_points == 26
+
+
+def test_build_mask_validates_card_ids() -> None:
+    from konkan_melds import build_mask
+
+    cards = [0, 63, 64, encoding.JOKER_IDS[1]]
+    assert build_mask(cards) == _mask_from_cards(cards)
+    assert build_mask([5, 5]) == build_mask([5])
+
+    with pytest.raises(ValueError, match="listed twice"):
+        build_mask([5, 5], strict=True)
+    with pytest.raises(ValueError, match="outside"):
+        build_mask([encoding.JOKER_IDS[1] + 1])
+
+
+
+def test_best_cover_weighted_trades_cards_for_points() -> None:
+    from konkan_melds import best_cover_weighted, parse_hand
+
+    # 2S J1 4S 5S melds four cards for 14 points; KH KD J1 three for 30.
+    mask_hi, mask_lo = parse_hand(["2S", "4S", "5S", "KH", "KD", "J1"])
+
+    cheap_points = best_cover_weighted(mask_hi, mask_lo, 1.0, 0.05, 0.0)
+    assert (cheap_points.covered_cards, cheap_points.total_points) == (4, 14)
+
+    dear_points = best_cover_weighted(mask_hi, mask_lo, 1.0, 0.1, 0.0)
+    assert (dear_points.covered_cards, dear_points.total_points) == (3, 30)
+
+    with pytest.raises(ValueError, match="non-negative"):
+        best_cover_weighted(mask_hi, mask_lo, 1.0, -1.0, 0.0)
+
+
+def test_inflated_point_tables_are_rejected_instead_of_overflowing() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import parse_hand
+
+    rank_points = [2**30] * 13
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "KH", "KD", "KC"])
+
+    with pytest.raises(ValueError, match="past the i32 range"):
+        native_enumerate_melds(mask_hi, mask_lo, rank_points=rank_points)
+    with pytest.raises(ValueError, match="past the i32 range"):
+        native_best_cover(mask_hi, mask_lo, OBJ_MAX_POINTS, 0, rank_points=rank_points)
+
+    # The largest table that fits still scores every meld exactly.
+    rank_points = [(2**31 - 1) // 128] * 13
+    melds = native_enumerate_melds(mask_hi, mask_lo, rank_points=rank_points)
+    assert [m.points for m in melds] == [3 * rank_points[0]] * 2
+    cover = native_best_cover(mask_hi, mask_lo, OBJ_MAX_POINTS, 0, rank_points=rank_points)
+    assert cover.total_points == 6 * rank_points[0]
+
+
+def test_joker_bonus_rescores_melds_and_must_fit_the_point_range() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import enumerate_sets, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["KS", "KH", "J2"])
+    sets = enumerate_sets(mask_hi, mask_lo, joker_bonus=(0, 5))
+    assert [m.points for m in sets] == [35]
+    cover = native_best_cover(mask_hi, mask_lo, OBJ_MAX_POINTS, 0, joker_bonus=(0, 5))
+    assert cover.total_points == 35
+
+    with pytest.raises(ValueError, match="past the i32 range"):
+        enumerate_sets(mask_hi, mask_lo, joker_bonus=(0, 2**30))
+    with pytest.raises(ValueError, match="past the i32 range"):
+        native_best_cover(mask_hi, mask_lo, OBJ_MAX_POINTS, 0, joker_bonus=(-(2**30), 0))
+
+
+def test_canonical_hand_key_ignores_deck_copies() -> None:
+    from konkan_melds import canonical_hand_key, parse_hand
+
+    def key(cards: list[str]) -> int:
+        return canonical_hand_key(*parse_hand(cards))
+
+    assert key(["KS", "7H", "J1"]) == key(["KS#2", "7H", "J2"])
+    assert key(["KS", "7H"]) != key(["KH", "7H"])
+    assert key(["KS"]) != key(["KS", "KS#2"])
+    assert key(["KS", "J1"]) != key(["KS", "J1", "J2"])
+
+
+def test_entry_points_reject_bits_past_the_card_ids() -> None:
+    import konkan_melds as native
+
+    hand = native.parse_hand(["5S", "6S", "7S"])
+    entry_points = {
+        "enumerate_melds": lambda hi, lo: native.enumerate_melds(hi, lo),
+        "enumerate_runs": lambda hi, lo: native.enumerate_runs(hi, lo),
+        "enumerate_sets": lambda hi, lo: native.enumerate_sets(hi, lo),
+        "enumerate_melds_stats": lambda hi, lo: native.enumerate_melds_stats(hi, lo),
+        "iter_melds": lambda hi, lo: native.iter_melds(hi, lo),
+        "count_melds": lambda hi, lo: native.count_melds(hi, lo),
+        "melds_by_card": lambda hi, lo: native.melds_by_card(hi, lo),
+        "melds_containing": lambda hi, lo: native.melds_containing(hi, lo, 4),
+        "melds_gained_by_draw": lambda hi, lo: native.melds_gained_by_draw(hi, lo, [7]),
+        "classify_meld": lambda hi, lo: native.classify_meld(hi, lo),
+        "near_runs": lambda hi, lo: native.near_runs(hi, lo),
+        "joker_swaps": lambda hi, lo: native.joker_swaps(hi, lo, *hand),
+        "layoff_candidates": lambda hi, lo: native.layoff_candidates(hi, lo, [hand]),
+        "canonical_hand_key": lambda hi, lo: native.canonical_hand_key(hi, lo),
+        "deadwood_points": lambda hi, lo: native.deadwood_points(hi, lo, 0, 0),
+        "deadwood_breakdown": lambda hi, lo: native.deadwood_breakdown(hi, lo, 0, 0),
+        "best_cover": lambda hi, lo: native.best_cover(hi, lo, OBJ_MAX_CARDS, 0),
+        "best_cover_constrained": lambda hi, lo: native.best_cover_constrained(
+            hi, lo, OBJ_MAX_CARDS, 0, 0, 0
+        ),
+        "required_mask": lambda hi, lo: native.best_cover_constrained(
+            *hand, OBJ_MAX_CARDS, 0, hi, lo
+        ),
+        "best_cover_parallel": lambda hi, lo: native.best_cover_parallel(hi, lo, OBJ_MAX_CARDS, 0),
+        "best_cover_dp": lambda hi, lo: native.best_cover_dp(hi, lo, OBJ_MAX_CARDS, 0),
+        "best_cover_batch": lambda hi, lo: native.best_cover_batch([(hi, lo)], OBJ_MAX_CARDS, 0),
+        "best_cover_timed": lambda hi, lo: native.best_cover_timed(hi, lo, OBJ_MAX_CARDS, 0, 100),
+        "best_cover_with_progress": lambda hi, lo: native.best_cover_with_progress(
+            hi, lo, OBJ_MAX_CARDS, 0, lambda cards, points: None
+        ),
+        "best_cover_weighted": lambda hi, lo: native.best_cover_weighted(hi, lo, 1.0, 1.0, 1.0),
+        "best_cover_joker_limited": lambda hi, lo: native.best_cover_joker_limited(
+            hi, lo, 0, OBJ_MAX_CARDS, 0
+        ),
+        "best_cover_pure": lambda hi, lo: native.best_cover_pure(hi, lo, OBJ_MAX_CARDS, 0),
+        "best_covers": lambda hi, lo: native.best_covers(hi, lo, OBJ_MAX_CARDS, 0, 2),
+        "all_maximal_covers": lambda hi, lo: native.all_maximal_covers(hi, lo, 2),
+        "best_single_meld": lambda hi, lo: native.best_single_meld(hi, lo, OBJ_MAX_CARDS),
+        "best_discard": lambda hi, lo: native.best_discard(hi, lo, OBJ_MIN_DEADWOOD, 0),
+        "discard_safety": lambda hi, lo: native.discard_safety(4, hi, lo),
+        "min_deadwood": lambda hi, lo: native.min_deadwood(hi, lo),
+        "can_go_out": lambda hi, lo: native.can_go_out(hi, lo),
+        "go_out_layout": lambda hi, lo: native.go_out_layout(hi, lo),
+        "rank_draws": lambda hi, lo: native.rank_draws(hi, lo, 0, 1 << 7, OBJ_MAX_CARDS),
+        "unseen_mask": lambda hi, lo: native.rank_draws(*hand, hi, lo, OBJ_MAX_CARDS),
+        "Solver.enumerate": lambda hi, lo: native.Solver().enumerate(hi, lo),
+    }
+    stray_hi = 1 << (106 - 64)
+    for name, entry_point in entry_points.items():
+        entry_point(*hand)
+        with pytest.raises(ValueError, match="bit 106 outside"):
+            entry_point(hand[0] | stray_hi, hand[1])
+            pytest.fail(f"{name} accepted a stray bit")
+
+    # One deck ends its IDs at 53, so card 60 of the two-deck layout is stray.
+    assert native.enumerate_melds(0, 1 << 60) == []
+    with pytest.raises(ValueError, match="outside card ids 0..54"):
+        native.enumerate_melds(0, 1 << 60, num_decks=1)
+
+
+def test_abstract_joker_bit_stands_in_for_any_joker() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import card_to_string, parse_card, parse_hand
+
+    abstract = 1 << 63
+    assert parse_card("J*") == 127
+    assert card_to_string(127) == "J*"
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "J*"])
+    assert mask_hi & abstract
+    melds = native_enumerate_melds(mask_hi, mask_lo)
+    assert melds and all(m.mask_hi & abstract and m.joker_ids == [127] for m in melds)
+
+    real = native_enumerate_melds(*parse_hand(["5S", "6S", "J1"]))
+    assert [m.points for m in melds] == [m.points for m in real]
+    cover = best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+    assert cover.covered_cards == 3 and cover.used_jokers == 1
+
+
+def test_melds_overlap_and_disjoint_all() -> None:
+    from konkan_melds import melds_disjoint_all, melds_overlap, parse_hand
+
+    run = parse_hand(["5S", "6S", "7S"])
+    sevens = parse_hand(["7S", "7H", "7D"])
+    kings = parse_hand(["KS", "KH", "J1"])
+    other_sevens = parse_hand(["7S#2", "7H", "J2"])
+
+    assert melds_overlap(*run, *sevens)
+    assert not melds_overlap(*run, *kings)
+    assert melds_disjoint_all([run, kings, other_sevens])
+    assert not melds_disjoint_all([run, kings, sevens])
+    assert melds_disjoint_all([])
+
+
+def test_binary_layouts_are_stable() -> None:
+    from konkan_melds import cover_from_bytes, cover_to_bytes, meld_from_bytes, meld_to_bytes
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import parse_hand
+
+    # 5S, J1, 7S: mask, 18 points, one joker, a run. These bytes never change.
+    meld_hex = "50000000000000000000000000010000" + "12000000" + "01" + "01"
+    meld = native_enumerate_melds(*parse_hand(["5S", "J1", "7S"]))[0]
+    assert meld_to_bytes(meld).hex() == meld_hex
+    decoded = meld_from_bytes(bytes.fromhex(meld_hex))
+    assert decoded == meld
+    assert (decoded.joker_ids, decoded.is_pure) == (meld.joker_ids, meld.is_pure)
+
+    # One meld, then KH left over (card 25) and only meets_threshold set.
+    cover_hex = "0100" + meld_hex + "00000002000000000000000000000000" + "01"
+    cover = best_cover(*parse_hand(["5S", "J1", "7S", "KH"]), OBJ_MAX_CARDS, 0)
+    assert cover_to_bytes(cover).hex() == cover_hex
+    decoded_cover = cover_from_bytes(bytes.fromhex(cover_hex))
+    assert cover_to_bytes(decoded_cover).hex() == cover_hex
+    assert decoded_cover.melds == cover.melds
+    assert (decoded_cover.covered_cards, decoded_cover.total_points) == (3, 18)
+    assert decoded_cover.deadwood == cover.deadwood
+
+    with pytest.raises(ValueError, match="22 bytes"):
+        meld_from_bytes(bytes(21))
+    with pytest.raises(ValueError, match="kind"):
+        meld_from_bytes(bytes(21) + b"\x07")
+    with pytest.raises(ValueError, match="takes"):
+        cover_from_bytes(bytes.fromhex(cover_hex)[:-1])
+
+
+def test_union_hands_reports_shared_copies_and_jokers() -> None:
+    from konkan_melds import parse_card, parse_hand, union_hands
+
+    a_hi, a_lo = parse_hand(["7H", "KS", "J1"])
+    b_hi, b_lo = parse_hand(["7H#2", "KS", "J1", "J2"])
+    hi, lo, collided = union_hands(a_hi, a_lo, b_hi, b_lo)
+
+    assert (hi, lo) == parse_hand(["7H", "7H#2", "KS", "J1", "J2"])
+    assert collided == [parse_card("KS"), parse_card("J1")]
+
+
+@pytest.mark.parametrize(
+    "cards, kind",
+    [
+        (["5S", "6S", "7S"], 1),
+        (["5S", "J1", "7S", "8S"], 1),
+        (["QS", "KS", "AS"], None),
+        (["7S", "7H", "7D", "7C"], 0),
+        (["7S", "J1", "7C"], 0),
+        (["7S", "7H"], None),
+        (["5S", "6S", "7H"], None),
+        (["7S", "7H", "8D"], None),
+        (["5S", "6S", "7S", "7H"], None),
+    ],
+)
+def test_classify_meld_recognises_exact_melds(cards: list[str], kind: int | None) -> None:
+    from konkan_melds import classify_meld, parse_hand
+
+    assert classify_meld(*parse_hand(cards)) == kind
+
+
+@pytest.mark.parametrize(
+    "cards, kind, error",
+    [
+        (["5S", "J1", "7S", "8S"], 1, None),
+        (["J1", "QS", "KS"], 1, None),
+        (["7S", "J1", "7C"], 0, None),
+        (["5S", "7S", "J1", "8S"], 1, "slot 1"),
+        (["QS", "KS", "J1"], 1, "past the ace"),
+        (["5S", "6H", "J1"], 1, "suit"),
+        (["7S", "J1"], 0, "3 to 4 cards"),
+        (["7S", "7S#2", "J1"], 0, None),
+        (["7S", "8H", "J1"], 0, "rank"),
+        (["J1", "J2", "7S", "7S"], 0, "twice"),
+    ],
+)
+def test_validate_meld_with_jokers(cards: list[str], kind: int, error: str | None) -> None:
+    from konkan_melds import parse_card, validate_meld_with_jokers
+
+    card_ids = [parse_card(card) for card in cards]
+    if error is None:
+        assert validate_meld_with_jokers(card_ids, kind)
+    else:
+        with pytest.raises(ValueError, match=error):
+            validate_meld_with_jokers(card_ids, kind)
+
+
+def test_meld_card_points_price_jokers_by_their_slot() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import parse_card, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["TS", "J1", "QS", "7H", "7D"])
+    melds = native_enumerate_melds(mask_hi, mask_lo)
+    by_cards = {frozenset(card for card, _ in m.card_points()): m for m in melds}
+
+    run = by_cards[frozenset(parse_card(card) for card in ("TS", "J1", "QS"))]
+    assert run.card_points() == sorted(
+        [(parse_card("TS"), 10), (parse_card("J1"), 10), (parse_card("QS"), 10)]
+    )
+    low_run = native_enumerate_melds(*parse_hand(["3S", "J1", "5S"]))[0]
+    assert dict(low_run.card_points())[parse_card("J1")] == 4
+
+    triple = by_cards[frozenset(parse_card(card) for card in ("7H", "7D", "J1"))]
+    assert [points for _, points in triple.card_points()] == [7, 7, 7]
+    assert all(sum(points for _, points in m.card_points()) == m.points for m in melds)
+
+
+def test_meld_joker_ids_name_the_physical_jokers() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import parse_card, parse_hand
+
+    first, second = parse_card("J1"), parse_card("J2")
+    melds = native_enumerate_melds(*parse_hand(["7H", "7D", "5S", "J1", "J2"]))
+
+    for m in melds:
+        held = {first, second} & set(card for card, _ in m.card_points())
+        assert m.joker_ids == sorted(held)
+        assert len(m.joker_ids) == m.jokers_used
+    assert {tuple(m.joker_ids) for m in melds} >= {(first,), (second,), (first, second)}
+
+
+def test_four_joker_layout_forms_two_joker_sets() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import mask_to_cards, parse_hand
+
+    natural_hi, natural_lo = parse_hand(["7H", "7D", "KS", "KH"])
+    # IDs 104..=107 sit past the standard deck, so set their bits directly.
+    joker_hi, joker_lo = encoding.split_mask(sum(1 << cid for cid in range(104, 108)))
+    mask_hi, mask_lo = natural_hi | joker_hi, natural_lo | joker_lo
+
+    # The default layout ends at ID 105, so the extra jokers are rejected.
+    with pytest.raises(ValueError, match="outside"):
+        native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+
+    melds = native_enumerate_melds(mask_hi, mask_lo, num_jokers=4)
+    assert any(
+        m.kind == 0 and {106, 107} <= set(mask_to_cards(m.mask_hi, m.mask_lo)) for m in melds
+    )
+    cover = native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0, num_jokers=4)
+    assert cover.covered_cards == 8
+    assert cover.used_jokers == 4
+    assert len(cover.melds) == 2
+
+
+def test_wild_deuces_substitute_but_count_separately_from_jokers() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import mask_to_strings, parse_hand
+
+    def melds_by_cards(cards: list[str], **kwargs: int) -> dict[tuple[str, ...], object]:
+        melds = native_enumerate_melds(*parse_hand(cards), **kwargs)
+        return {tuple(mask_to_strings(m.mask_hi, m.mask_lo)): m for m in melds}
+
+    assert melds_by_cards(["7H", "7D", "2S"]) == {}
+    wild_set = melds_by_cards(["7H", "7D", "2S"], wild_rank=1)[("2S", "7H", "7D")]
+    assert (wild_set.kind, wild_set.points) == (0, 21)
+    assert (wild_set.jokers_used, wild_set.wilds_used, wild_set.is_pure) == (0, 1, False)
+
+    wild_run = melds_by_cards(["5S", "6S", "2H"], wild_rank=1)[("5S", "6S", "2H")]
+    assert (wild_run.kind, wild_run.points, wild_run.wilds_used) == (1, 18, 1)
+
+    # A deuce in its own slot is a natural card worth its own points.
+    natural_run = melds_by_cards(["AS", "2S", "3S"], wild_rank=1)[("AS", "2S", "3S")]
+    assert (natural_run.points, natural_run.wilds_used, natural_run.is_pure) == (15, 0, True)
+
+
+def test_melds_gained_by_draw_matches_enumerating_each_draw() -> None:
+    from konkan_melds import melds_gained_by_draw
+
+    hand = _FIXED_HANDS[0]
+    mask_hi, mask_lo = _mask_from_cards(hand)
+    candidates = list(range(encoding.DECK_CARD_COUNT))
+    gained = melds_gained_by_draw(mask_hi, mask_lo, candidates)
+
+    assert [card for card, _ in gained] == candidates
+    for card, melds in gained:
+        if card in hand:
+            assert melds == []
+            continue
+        drawn_hi, drawn_lo = _mask_from_cards([*hand, card])
+        expected = [
+            m
+            for m in enumerate_melds(drawn_hi, drawn_lo)
+            if card in _cards_from_meld(m.mask_hi, m.mask_lo)
+        ]
+        assert melds == expected
+
+
+def test_near_runs_names_the_missing_middle_cards() -> None:
+    from konkan_melds import near_runs, parse_card, parse_hand
+
+    six = [parse_card("6S"), parse_card("6S#2")]
+    five, seven, eight = (parse_card(card) for card in ("5S", "7S", "8S"))
+    assert near_runs(*parse_hand(["5S", "7S", "8S", "KH"])) == [
+        ([five, seven], six),
+        ([five, seven, eight], six),
+    ]
+    assert near_runs(*parse_hand(["5S", "8S"])) == []
+    assert near_runs(*parse_hand(["5S", "8S"]), max_missing=2) == [
+        ([five, eight], six + [parse_card("7S"), parse_card("7S#2")]),
+    ]
+
+    # A joker already turns 5S 7S into a run.
+    assert near_runs(*parse_hand(["5S", "7S", "J1"])) == []
+
+
+def test_enumerate_melds_only_maximal_runs_drops_sub_runs() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import parse_hand
+
+    mask_hi, mask_lo = parse_hand(["3S", "4S", "5S", "6S", "7S", "9H", "TH", "JH"])
+    assert len(native_enumerate_melds(mask_hi, mask_lo)) == 7
+
+    maximal = native_enumerate_melds(mask_hi, mask_lo, only_maximal_runs=True)
+    assert sorted(len(_cards_from_meld(m.mask_hi, m.mask_lo)) for m in maximal) == [3, 5]
+
+
+@pytest.mark.parametrize("ace_mode", [ACE_LOW, ACE_HIGH_LOW, ACE_WRAP])
+def test_count_melds_matches_enumeration(ace_mode: int) -> None:
+    from konkan_melds import count_melds
+
+    for cards in _FIXED_HANDS:
+        mask_hi, mask_lo = _mask_from_cards(cards)
+        expected = len(enumerate_melds(mask_hi, mask_lo, ace_mode=ace_mode))
+        assert count_melds(mask_hi, mask_lo, ace_mode) == expected
+
+
+def test_enumerate_melds_caps_run_length() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+    from konkan_melds import parse_hand
+
+    mask_hi, mask_lo = parse_hand(["3S", "4S", "5S", "6S", "7S", "8S", "9S"])
+    lengths = [
+        len(_cards_from_meld(m.mask_hi, m.mask_lo))
+        for m in native_enumerate_melds(mask_hi, mask_lo, max_run_len=4)
+    ]
+    assert sorted(lengths) == [3] * 5 + [4] * 4
+
+    maximal = native_enumerate_melds(mask_hi, mask_lo, max_run_len=4, only_maximal_runs=True)
+    assert len(maximal) == 4
+
+
+def test_enumerate_melds_presentation_orders() -> None:
+    from konkan_melds import enumerate_melds as native_enumerate_melds
+
+    mask_hi, mask_lo = _mask_from_cards(_FIXED_HANDS[0])
+    by_mask = native_enumerate_melds(mask_hi, mask_lo)
+    by_points = native_enumerate_melds(mask_hi, mask_lo, order=1)
+    by_cards = native_enumerate_melds(mask_hi, mask_lo, order=2)
+
+    assert sorted(by_points, key=lambda m: (-m.points, m.jokers_used)) == by_points
+    card_counts = [len(_cards_from_meld(m.mask_hi, m.mask_lo)) for m in by_cards]
+    assert card_counts == sorted(card_counts, reverse=True)
+    assert sorted(by_points, key=lambda m: (m.mask_hi, m.mask_lo)) == sorted(
+        by_mask, key=lambda m: (m.mask_hi, m.mask_lo)
+    )
+
+
+def test_melds_by_card_indexes_enumerated_melds() -> None:
+    from konkan_melds import melds_by_card
+
+    hand = _FIXED_HANDS[0]
+    mask_hi, mask_lo = _mask_from_cards(hand)
+    melds = enumerate_melds(mask_hi, mask_lo)
+    buckets = melds_by_card(mask_hi, mask_lo)
+
+    assert [card for card, _ in buckets] == sorted(set(hand))
+    for card, indices in buckets:
+        assert indices == [
+            idx
+            for idx, m in enumerate(melds)
+            if card in _cards_from_meld(m.mask_hi, m.mask_lo)
+        ]
+
+
+def test_go_out_allows_one_leftover_card() -> None:
+    from konkan_melds import can_go_out, go_out_layout, parse_card, parse_hand
+
+    melded = ["3S", "4S", "5S", "6S", "7H", "7D", "J1", "TC", "JC", "QC", "9D", "9H", "9C"]
+    mask_hi, mask_lo = parse_hand([*melded, "2H"])
+    assert can_go_out(mask_hi, mask_lo)
+    layout = go_out_layout(mask_hi, mask_lo)
+    assert layout is not None
+    assert layout.deadwood == [parse_card("2H")]
+    assert layout.covered_cards == 13
+
+    stuck_hi, stuck_lo = parse_hand([*melded[:-1], "2H", "5D"])
+    assert not can_go_out(stuck_hi, stuck_lo)
+    assert go_out_layout(stuck_hi, stuck_lo) is None
+
+
+def test_layoff_candidates_extend_table_melds() -> None:
+    from konkan_melds import layoff_candidates, parse_card, parse_hand
+
+    table = [
+        parse_hand(["5S", "6S", "7S"]),
+        parse_hand(["KH", "KD", "KC"]),
+        parse_hand(["9H", "J1", "JH"]),
+    ]
+    hand_hi, hand_lo = parse_hand(["8S", "KS", "TH", "2C"])
+    run, kind_set = 1, 0
+
+    extensions = {
+        (parse_card("8S"), 0, run, None),
+        (parse_card("KS"), 1, kind_set, None),
+        (parse_card("TH"), 2, run, None),
+    }
+    assert set(layoff_candidates(hand_hi, hand_lo, table)) == extensions
+
+    # The ten of hearts may also take the joker's place in 9H J1 JH.
+    swapped = layoff_candidates(hand_hi, hand_lo, table, swap_jokers=True)
+    assert set(swapped) == extensions | {(parse_card("TH"), 2, run, parse_card("J1"))}
+    assert swapped == sorted(swapped, key=lambda entry: (entry[0], entry[1]))
+
+
+def test_joker_swaps_take_the_slot_the_joker_fills() -> None:
+    from konkan_melds import joker_swaps, parse_card, parse_hand
+
+    hand_hi, hand_lo = parse_hand(["6S", "6S#2", "8S", "KS", "QS", "J2"])
+
+    # In 5S J1 7S the joker can only be the six of spades, either copy.
+    assert joker_swaps(hand_hi, hand_lo, *parse_hand(["5S", "J1", "7S"])) == [
+        (parse_card("6S"), parse_card("J1")),
+        (parse_card("6S#2"), parse_card("J1")),
+    ]
+    # In KH KD J1 it stands for any king.
+    assert joker_swaps(hand_hi, hand_lo, *parse_hand(["KH", "KD", "J1"])) == [
+        (parse_card("KS"), parse_card("J1")),
+    ]
+    assert joker_swaps(hand_hi, hand_lo, *parse_hand(["KH", "KD", "KC"])) == []
+
+
+def test_rank_draws_weights_improvement_by_copies_unseen() -> None:
+    from konkan_melds import parse_card, parse_hand, rank_draws
+
+    hand_hi, hand_lo = parse_hand(["5S", "6S", "9H"])
+    unseen_hi, unseen_lo = parse_hand(["7S", "7S#2", "4S", "KD"])
+    ranked = rank_draws(hand_hi, hand_lo, unseen_hi, unseen_lo, OBJ_MIN_DEADWOOD)
+
+    # Either end of the run melds three cards; the sevens are twice as likely.
+    assert ranked == [
+        (parse_card("7S"), pytest.approx(3 * 2 / 4)),
+        (parse_card("4S"), pytest.approx(3 / 4)),
+        (parse_card("KD"), 0.0),
+    ]
+
+
+def test_best_cover_with_progress_reports_and_reraises() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import best_cover_with_progress, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "KH", "KD", "KC", "2D"])
+    reports: list[tuple[int, int]] = []
+
+    def record(covered: int, points: int) -> None:
+        reports.append((covered, points))
+
+    cover = best_cover_with_progress(mask_hi, mask_lo, OBJ_MAX_CARDS, 0, record)
+
+    # Reports are throttled, but the first best found is always passed on.
+    assert reports and reports[0] == (0, 0)
+    assert all(covered <= cover.covered_cards for covered, _ in reports)
+    plain = native_best_cover(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+    assert cover.covered_cards == plain.covered_cards
+
+    def fail(covered: int, points: int) -> None:
+        raise RuntimeError("stop")
+
+    with pytest.raises(RuntimeError, match="stop"):
+        best_cover_with_progress(mask_hi, mask_lo, OBJ_MAX_CARDS, 0, fail)
+
+
+def test_best_discard_keeps_melds_and_sheds_high_deadwood() -> None:
+    from konkan_melds import best_discard, parse_card, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "KH", "3D", "J1"])
+    discard, cover = best_discard(mask_hi, mask_lo, OBJ_MAX_CARDS, 0)
+
+    assert discard == parse_card("KH")
+    assert cover.covered_cards == 4
+    assert cover.deadwood == [parse_card("3D")]
+
+    jokers_only = best_discard(*parse_hand(["J1", "J2"]), OBJ_MAX_CARDS, 0)
+    assert jokers_only[0] == parse_card("J1")
+    with pytest.raises(ValueError):
+        best_discard(0, 0, OBJ_MAX_CARDS, 0)
+
+
+def test_joker_penalty_keeps_leftover_jokers_in_melds() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import parse_card, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["7H", "7D", "7S", "7C", "J1"])
+    free = native_best_cover(mask_hi, mask_lo, OBJ_MIN_DEADWOOD, 0)
+    assert free.deadwood == [parse_card("J1")]
+    assert free.used_jokers == 0
+
+    # Charging for the idle joker makes melding it and shedding a seven cheaper.
+    charged = native_best_cover(mask_hi, mask_lo, OBJ_MIN_DEADWOOD, 0, joker_penalty=25)
+    assert charged.used_jokers == 1
+    assert charged.deadwood_count == 1
+    assert parse_card("J1") not in charged.deadwood
+
+
+def test_joker_penalty_outside_its_range_raises() -> None:
+    from konkan_melds import best_cover as native_best_cover
+    from konkan_melds import best_cover_constrained, parse_hand
+
+    mask_hi, mask_lo = parse_hand(["5S", "6S", "7S", "KH", "J1", "J2"])
+    largest = (2**31 - 1) // 128
+    cover = native_best_cover(mask_hi, mask_lo, OBJ_MIN_DEADWOOD, 0, joker_penalty=largest)
+    assert cover.covered_cards == 6
+    for joker_penalty in (largest + 1, 2**31 - 1, -1):
+        with pytest.raises(ValueError, match="joker_penalty"):
+            native_best_cover(mask_hi, mask_lo, OBJ_MIN_DEADWOOD, 0, joker_penalty=joker_penalty)
+        with pytest.raises(ValueError, match="joker_penalty"):
+            best_cover_constrained(
+                mask_hi, mask_lo, OBJ_MIN_DEADWOOD, 0, 0, 0, joker_penalty=joker_penalty
+            )